@@ -0,0 +1,89 @@
+#[macro_use]
+extern crate criterion;
+extern crate fesl_codec;
+
+use criterion::{black_box, Criterion, Throughput};
+use fesl_codec::fesl::*;
+
+fn build_small() -> FeslMessage {
+    let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 1);
+    builder.push("TXN", "Hello");
+    builder.push("clientString", "mohair-pc");
+    builder.push_u32("sku", 1829831);
+    builder.push("locale", "en_US");
+    builder.push("clientPlatform", "PC");
+    builder.push("clientVersion", "1.1");
+    builder.push("SDKVersion", "3.5.2.0.9");
+    builder.push("protocolVersion", "2.0");
+    builder.push_u32("fragmentSize", 8096);
+    builder.push("clientType", "server");
+    builder.build()
+}
+
+// roughly the shape of a rank GetStats response for a full server
+fn build_large() -> FeslMessage {
+    let mut builder = FeslMessageBuilder::new("rank", FeslMessageType::SingleServer, 1);
+    builder.push("TXN", "GetStats");
+    builder.push_u32("stats.[]", 2000);
+    for i in 0..2000 {
+        builder.push(format!("stats.{}.key", i), format!("c_kit{}_score", i));
+        builder.push_i64(format!("stats.{}.value", i), i * 1337);
+        builder.push(format!("stats.{}.text", i), "player name with spaces");
+    }
+    builder.build()
+}
+
+// many flat keys, the worst case for building the value tree from untrusted input
+fn build_wide() -> FeslMessage {
+    let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 1);
+    builder.push("TXN", "NuLogin");
+    for i in 0..32000 {
+        builder.push(format!("a{}", i), "");
+    }
+    builder.build()
+}
+
+fn parse(c: &mut Criterion, name: &str, msg: &FeslMessage) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(msg.as_bytes().len() as u64));
+    group.bench_function("iterate", |b| b.iter(|| {
+        black_box(msg).into_iter().map(Result::unwrap).count()
+    }));
+    group.bench_function("decode", |b| b.iter(|| {
+        let mut decoder = FeslDecoder::new();
+        decoder.push(black_box(msg.as_bytes()));
+        decoder.decode().unwrap().unwrap()
+    }));
+    group.bench_function("value", |b| b.iter(|| {
+        FeslValue::from_message(black_box(msg)).unwrap()
+    }));
+    group.bench_function("rebuild", |b| b.iter(|| {
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 1);
+        for pair in black_box(msg) {
+            let (key, value) = pair.unwrap();
+            builder.push_raw(key, value);
+        }
+        builder.build()
+    }));
+    group.finish();
+}
+
+fn bench_small(c: &mut Criterion) {
+    parse(c, "small", &build_small());
+}
+
+fn bench_large(c: &mut Criterion) {
+    parse(c, "large", &build_large());
+}
+
+fn bench_wide(c: &mut Criterion) {
+    parse(c, "wide", &build_wide());
+}
+
+fn bench_build(c: &mut Criterion) {
+    c.bench_function("build/small", |b| b.iter(build_small));
+    c.bench_function("build/large", |b| b.iter(build_large));
+}
+
+criterion_group!(benches, bench_small, bench_large, bench_wide, bench_build);
+criterion_main!(benches);
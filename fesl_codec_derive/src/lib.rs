@@ -0,0 +1,197 @@
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, Ident, LitByteStr, LitStr};
+
+struct TransactionAttrs {
+    cmd: LitByteStr,
+    txn: LitStr,
+    fesl_type: Ident
+}
+
+fn parse_transaction_attrs(input: &DeriveInput) -> syn::Result<TransactionAttrs> {
+    let mut cmd = None;
+    let mut txn = None;
+    let mut fesl_type = None;
+    for attr in input.attrs.iter().filter(|x| x.path().is_ident("fesl")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("cmd") {
+                let value: LitStr = meta.value()?.parse()?;
+                if value.value().len() != 4 {
+                    return Err(meta.error("fesl cmd must be a 4 character string"));
+                }
+                cmd = Some(LitByteStr::new(value.value().as_bytes(), value.span()));
+            } else if meta.path.is_ident("txn") {
+                txn = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("type") {
+                let value: LitStr = meta.value()?.parse()?;
+                match &value.value()[..] {
+                    "SingleClient" | "SingleServer" | "MultiClient" | "MultiServer" => (),
+                    _ => return Err(meta.error("unknown fesl type"))
+                }
+                fesl_type = Some(Ident::new(&value.value(), value.span()));
+            } else {
+                return Err(meta.error("unsupported fesl attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    match (cmd, txn) {
+        (Some(cmd), Some(txn)) => Ok(TransactionAttrs {
+            cmd,
+            txn,
+            fesl_type: fesl_type.unwrap_or_else(|| Ident::new("SingleClient", Span::call_site()))
+        }),
+        _ => Err(syn::Error::new_spanned(&input.ident, "expected #[fesl(cmd = \"...\", txn = \"...\")]"))
+    }
+}
+
+fn parse_field_key(field: &syn::Field) -> syn::Result<LitStr> {
+    let ident = field.ident.as_ref().unwrap();
+    let mut key = LitStr::new(&ident.to_string(), ident.span());
+    for attr in field.attrs.iter().filter(|x| x.path().is_ident("fesl")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                key = meta.value()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported fesl attribute"))
+            }
+        })?;
+    }
+    Ok(key)
+}
+
+fn parse_fields(input: &DeriveInput, derive: &str) -> syn::Result<(Vec<Ident>, Vec<LitStr>)> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, format!("{} requires named fields", derive)))
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, format!("{} can only be derived for structs", derive)))
+    };
+    let mut idents = Vec::new();
+    let mut keys = Vec::new();
+    for field in fields {
+        idents.push(field.ident.clone().unwrap());
+        keys.push(parse_field_key(field)?);
+    }
+    Ok((idents, keys))
+}
+
+// pushes each field that has a value onto `entries`
+fn expand_to_entries(idents: &[Ident], keys: &[LitStr]) -> proc_macro2::TokenStream {
+    quote! {
+        #(
+            if let ::fesl_codec::__private::Option::Some(value) = ::fesl_codec::fesl::FeslField::to_value(&self.#idents) {
+                entries.push((::fesl_codec::__private::String::from(#keys), value));
+            }
+        )*
+    }
+}
+
+// builds the struct from the map in `value`, naming fields in errors relative to `prefix`
+fn expand_from_entries(name: &Ident, idents: &[Ident], keys: &[LitStr]) -> proc_macro2::TokenStream {
+    quote! {
+        #[allow(unused_variables)]
+        let join = |key: &str| -> ::fesl_codec::__private::String {
+            let mut buf = ::fesl_codec::__private::String::from(prefix);
+            if !prefix.is_empty() {
+                buf.push('.');
+            }
+            buf.push_str(key);
+            buf
+        };
+        ::fesl_codec::__private::Result::Ok(#name {
+            #(
+                #idents: ::fesl_codec::fesl::FeslField::from_value(&join(#keys), value.get(#keys))?,
+            )*
+        })
+    }
+}
+
+fn expand_transaction(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let attrs = parse_transaction_attrs(&input)?;
+    let (idents, keys) = parse_fields(&input, "FeslTransaction")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let TransactionAttrs { cmd, txn, fesl_type } = attrs;
+    let to_entries = expand_to_entries(&idents, &keys);
+    let from_entries = expand_from_entries(name, &idents, &keys);
+    Ok(quote! {
+        impl #impl_generics ::fesl_codec::fesl::FeslTransaction for #name #ty_generics #where_clause {
+            const CMD: ::fesl_codec::fesl::FeslCommand = ::fesl_codec::fesl::FeslCommand::new(*#cmd);
+            const TXN: &'static str = #txn;
+            const TYPE: ::fesl_codec::fesl::FeslMessageType = ::fesl_codec::fesl::FeslMessageType::#fesl_type;
+
+            fn to_value(&self) -> ::fesl_codec::fesl::FeslValue {
+                let mut entries = ::fesl_codec::__private::Vec::new();
+                entries.push((
+                    ::fesl_codec::__private::String::from("TXN"),
+                    ::fesl_codec::fesl::FeslValue::String(::fesl_codec::__private::String::from(#txn))
+                ));
+                #to_entries
+                ::fesl_codec::fesl::FeslValue::Map(entries)
+            }
+
+            fn from_value(value: &::fesl_codec::fesl::FeslValue) -> ::fesl_codec::__private::Result<Self, ::fesl_codec::Error> {
+                let prefix = "";
+                #from_entries
+            }
+        }
+    })
+}
+
+fn expand_field(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let (idents, keys) = parse_fields(&input, "FeslField")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let to_entries = expand_to_entries(&idents, &keys);
+    let from_entries = expand_from_entries(name, &idents, &keys);
+    Ok(quote! {
+        impl #impl_generics ::fesl_codec::fesl::FeslField for #name #ty_generics #where_clause {
+            fn to_value(&self) -> ::fesl_codec::__private::Option<::fesl_codec::fesl::FeslValue> {
+                #[allow(unused_mut)]
+                let mut entries = ::fesl_codec::__private::Vec::new();
+                #to_entries
+                ::fesl_codec::__private::Option::Some(::fesl_codec::fesl::FeslValue::Map(entries))
+            }
+
+            fn from_value(key: &str, value: ::fesl_codec::__private::Option<&::fesl_codec::fesl::FeslValue>) -> ::fesl_codec::__private::Result<Self, ::fesl_codec::Error> {
+                let (prefix, value) = match value {
+                    ::fesl_codec::__private::Option::Some(value @ ::fesl_codec::fesl::FeslValue::Map(_)) => (key, value),
+                    ::fesl_codec::__private::Option::Some(_) => {
+                        return ::fesl_codec::__private::Result::Err(::fesl_codec::Error::new(::fesl_codec::ErrorKind::ExpectedMap).with_key(key));
+                    }
+                    ::fesl_codec::__private::Option::None => {
+                        return ::fesl_codec::__private::Result::Err(::fesl_codec::ErrorKind::MissingKey(::fesl_codec::__private::String::from(key)).into());
+                    }
+                };
+                #from_entries
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(FeslTransaction, attributes(fesl))]
+pub fn derive_fesl_transaction(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match expand_transaction(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into()
+    }
+}
+
+#[proc_macro_derive(FeslField, attributes(fesl))]
+pub fn derive_fesl_field(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match expand_field(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into()
+    }
+}
@@ -0,0 +1,182 @@
+use core::error;
+use core::fmt;
+use core::result;
+use core::str;
+use alloc::string::{String, ToString};
+#[cfg(feature = "std")]
+use std::io;
+use base64;
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    ConflictingKey(String),
+    Custom(String),
+    ExpectedDelimiter,
+    ExpectedList,
+    ExpectedMap,
+    ExpectedString,
+    ExpectedTerminator,
+    ExpectedUtf8(str::Utf8Error),
+    FragmentSizeMismatch(usize, usize),
+    FragmentSizeTooSmall(usize),
+    Incomplete(usize),
+    InvalidBase64(base64::DecodeError),
+    InvalidCommandLength,
+    InvalidFragment,
+    InvalidKey(String),
+    InvalidListIndex(String),
+    InvalidListLength(String),
+    InvalidType(u8),
+    InvalidValue(String),
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    ListLengthMismatch(String, usize, usize),
+    MessageTooLarge(usize),
+    MessageTooSmall(usize),
+    MismatchedReply(u32),
+    MissingKey(String),
+    TooManyPending(usize),
+    TrailingBytes(usize),
+    UnexpectedCommand(String),
+    UnexpectedTransaction(String),
+    UnknownRequest(u32),
+    UnsupportedType(&'static str)
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::ConflictingKey(ref key) => write!(f, "key {:?} conflicts with another key", key),
+            ErrorKind::Custom(ref msg) => f.write_str(msg),
+            ErrorKind::ExpectedDelimiter => f.write_str("expected delimiter"),
+            ErrorKind::ExpectedList => f.write_str("expected a list"),
+            ErrorKind::ExpectedMap => f.write_str("expected a struct or map at the top level"),
+            ErrorKind::ExpectedString => f.write_str("expected a string"),
+            ErrorKind::ExpectedTerminator => f.write_str("expected 0x00 terminator"),
+            ErrorKind::ExpectedUtf8(ref error) => write!(f, "expected utf-8: {}", error),
+            ErrorKind::FragmentSizeMismatch(expected, actual) => write!(f, "fragment size mismatch: expected {} bytes, got {}", expected, actual),
+            ErrorKind::FragmentSizeTooSmall(size) => write!(f, "fragment size {} is too small to carry any data", size),
+            ErrorKind::Incomplete(len) => write!(f, "message is incomplete after {} bytes", len),
+            ErrorKind::InvalidBase64(ref error) => write!(f, "invalid base64 fragment data: {}", error),
+            ErrorKind::InvalidCommandLength => f.write_str("command must be 4 bytes"),
+            ErrorKind::InvalidFragment => f.write_str("fragment size, decodedSize or data is missing or inconsistent"),
+            ErrorKind::InvalidKey(ref key) => write!(f, "key {:?} contains a delimiter", key),
+            ErrorKind::InvalidListIndex(ref key) => write!(f, "invalid list index in key {:?}", key),
+            ErrorKind::InvalidListLength(ref key) => write!(f, "invalid list length in key {:?}", key),
+            ErrorKind::InvalidType(val) => write!(f, "invalid message type 0x{:02x}", val),
+            ErrorKind::InvalidValue(ref value) => write!(f, "invalid value {:?}", value),
+            #[cfg(feature = "std")]
+            ErrorKind::Io(ref error) => write!(f, "io error: {}", error),
+            ErrorKind::ListLengthMismatch(ref key, expected, actual) => write!(f, "list {:?} declares {} entries but has {}", key, expected, actual),
+            ErrorKind::MessageTooLarge(len) => write!(f, "message length {} is too large", len),
+            ErrorKind::MessageTooSmall(len) => write!(f, "message length {} is too small", len),
+            ErrorKind::MismatchedReply(id) => write!(f, "reply to request {} has a different command or transaction", id),
+            ErrorKind::MissingKey(ref key) => write!(f, "missing key {:?}", key),
+            ErrorKind::TooManyPending(len) => write!(f, "{} fragmented messages are already pending", len),
+            ErrorKind::TrailingBytes(len) => write!(f, "{} trailing bytes after end of message", len),
+            ErrorKind::UnexpectedCommand(ref cmd) => write!(f, "unexpected command {:?}", cmd),
+            ErrorKind::UnexpectedTransaction(ref txn) => write!(f, "unexpected transaction {:?}", txn),
+            ErrorKind::UnknownRequest(id) => write!(f, "no request with id {} is pending", id),
+            ErrorKind::UnsupportedType(name) => write!(f, "unsupported type: {}", name)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: Option<usize>,
+    key: Option<String>
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Error {
+        Error {
+            kind,
+            offset: None,
+            key: None
+        }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_ref().map(|x| &x[..])
+    }
+
+    // the innermost location wins, so these never overwrite what is already set
+    pub fn at(mut self, offset: usize) -> Error {
+        self.offset = self.offset.or(Some(offset));
+        self
+    }
+
+    pub fn with_key(mut self, key: &str) -> Error {
+        if self.key.is_none() {
+            self.key = Some(key.to_string());
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(ref key) = self.key {
+            write!(f, " for key {:?}", key)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::ExpectedUtf8(ref error) => Some(error),
+            #[cfg(feature = "std")]
+            ErrorKind::InvalidBase64(ref error) => Some(error),
+            #[cfg(feature = "std")]
+            ErrorKind::Io(ref error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error::new(kind)
+    }
+}
+
+impl From<str::Utf8Error> for Error {
+    fn from(error: str::Utf8Error) -> Self {
+        Error::new(ErrorKind::ExpectedUtf8(error))
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::new(ErrorKind::Io(error))
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(error: base64::DecodeError) -> Self {
+        Error::new(ErrorKind::InvalidBase64(error))
+    }
+}
+
+pub type Result<T> = result::Result<T, Error>;
@@ -1,120 +1,515 @@
 extern crate byteorder;
 
-use std::str;
-use std::io::{Read, Error};
-use std::result::Result;
-use self::byteorder::{ByteOrder, BigEndian, WriteBytesExt};
-use num_traits::{FromPrimitive};
-
-#[derive(Debug, PartialEq, Primitive)]
-#[repr(u8)]
+use core::str;
+use core::ops::Range;
+use core::convert::TryFrom;
+use core::result::Result;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::io::{IoSlice, Read, Write};
+// the index is shared between threads when std is available
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+#[cfg(not(feature = "std"))]
+use core::cell::OnceCell as OnceLock;
+use self::byteorder::{ByteOrder, BigEndian};
+use memchr::{memchr, memchr2};
+use error::{Error, ErrorKind};
+
+#[cfg(feature = "std")]
+mod client;
+mod command;
+mod edit;
+mod error_response;
+mod escape;
+mod fragment;
+mod index;
+mod router;
+mod text;
+mod transaction;
+mod value;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "tokio")]
+mod codec;
+
+pub mod acct;
+pub mod fsys;
+
+#[cfg(feature = "std")]
+pub use self::client::{FeslClient, FeslClientPolicy};
+pub use self::command::FeslCommand;
+pub use self::error_response::{FeslErrorCode, FeslErrorResponse, FeslFieldError};
+pub use self::escape::{escape, unescape, FeslUnescapedIterator};
+pub use self::fragment::{FeslFragmentAssembler, FeslFragmentPolicy};
+use self::index::FeslMessageIndex;
+pub use self::router::{FeslReply, FeslRouter};
+pub use self::text::{FeslDecodedIterator, FeslTextMode};
+pub use self::value::FeslValue;
+pub use self::transaction::{FeslField, FeslTransaction};
+pub use fesl_codec_derive::{FeslField, FeslTransaction};
+#[cfg(feature = "serde")]
+pub use self::serialize::{from_message, to_builder};
+#[cfg(feature = "tokio")]
+pub use self::codec::FeslCodec;
+
+#[derive(Debug, Clone, Copy)]
 pub enum FeslMessageType {
-    SingleClient = 0xc0,
-    SingleServer = 0x80,
-    MultiClient = 0xf0,
-    MultiServer = 0xb0
+    SingleClient,
+    SingleServer,
+    MultiClient,
+    MultiServer,
+    // upper nibbles used by Theater and some game builds, kept so traffic can be replayed as is
+    Other(u8)
 }
 
-#[derive(Debug)]
-pub enum FeslMessageError {
-    ExpectedDelimiter,
-    ExpectedUtf8(str::Utf8Error),
-    InvalidCommandLength,
-    InvalidType(u8)
+impl FeslMessageType {
+    pub fn from_bits(val: u8) -> FeslMessageType {
+        match val & 0xf0 {
+            0xc0 => FeslMessageType::SingleClient,
+            0x80 => FeslMessageType::SingleServer,
+            0xf0 => FeslMessageType::MultiClient,
+            0xb0 => FeslMessageType::MultiServer,
+            val => FeslMessageType::Other(val)
+        }
+    }
+
+    // the type used to answer a message of this type
+    pub fn reply(self) -> FeslMessageType {
+        match FeslMessageType::from_bits(self.bits()) {
+            FeslMessageType::SingleClient | FeslMessageType::MultiClient => FeslMessageType::SingleServer,
+            FeslMessageType::SingleServer | FeslMessageType::MultiServer => FeslMessageType::SingleClient,
+            other => other
+        }
+    }
+
+    pub fn bits(self) -> u8 {
+        match self {
+            FeslMessageType::SingleClient => 0xc0,
+            FeslMessageType::SingleServer => 0x80,
+            FeslMessageType::MultiClient => 0xf0,
+            FeslMessageType::MultiServer => 0xb0,
+            FeslMessageType::Other(val) => val & 0xf0
+        }
+    }
 }
 
-impl From<str::Utf8Error> for FeslMessageError {
-    fn from(error: str::Utf8Error) -> Self {
-        FeslMessageError::ExpectedUtf8(error)
+// compares the encoded bits, so Other(0xc0) is the same type as SingleClient
+impl PartialEq for FeslMessageType {
+    fn eq(&self, other: &FeslMessageType) -> bool {
+        self.bits() == other.bits()
     }
 }
 
-type FeslMessageResult<T> = Result<T, FeslMessageError>;
+impl Eq for FeslMessageType {}
+
+type FeslMessageResult<T> = Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeslDecodePolicy {
+    pub max_len: usize,
+    pub min_len: usize,
+    pub require_terminator: bool,
+    pub reject_trailing: bool
+}
+
+impl FeslDecodePolicy {
+    // the header alone is 12 bytes, so anything shorter can never be framed
+    pub fn check_len(&self, len: usize) -> FeslMessageResult<()> {
+        if len < 12 || len < self.min_len {
+            return Err(Error::new(ErrorKind::MessageTooSmall(len)).at(8));
+        }
+        if len > self.max_len {
+            return Err(Error::new(ErrorKind::MessageTooLarge(len)).at(8));
+        }
+        Ok(())
+    }
+
+    // offsets are reported from the start of the message, and the body starts after the header
+    pub fn check_body(&self, body: &[u8]) -> FeslMessageResult<()> {
+        if self.require_terminator && body.last() != Some(&0x00) {
+            return Err(Error::new(ErrorKind::ExpectedTerminator).at(12 + body.len().saturating_sub(1)));
+        }
+        if self.reject_trailing {
+            if let Some(x) = body.iter().position(|&x| x == 0x00) {
+                if x + 1 < body.len() {
+                    return Err(Error::new(ErrorKind::TrailingBytes(body.len() - x - 1)).at(12 + x + 1));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn frame_len(&self, src: &[u8]) -> FeslMessageResult<Option<usize>> {
+        if src.len() < 12 {
+            return Ok(None);
+        }
+        let len = BigEndian::read_u32(&src[8..12]) as usize;
+        self.check_len(len)?;
+        if src.len() < len {
+            return Ok(None);
+        }
+        self.check_body(&src[12..len])?;
+        Ok(Some(len))
+    }
+}
+
+impl Default for FeslDecodePolicy {
+    fn default() -> Self {
+        FeslDecodePolicy {
+            max_len: 0x100000,
+            min_len: 12,
+            require_terminator: false,
+            reject_trailing: false
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct FeslMessage {
-    data: Box<[u8]>
+    data: Box<[u8]>,
+    index: OnceLock<FeslMessageIndex>
 }
 
 impl FeslMessage {
+    fn new(data: Box<[u8]>) -> FeslMessage {
+        FeslMessage {
+            data,
+            index: OnceLock::new()
+        }
+    }
+
     // TODO: implement more sources in single `from(src)` method signature
-    // TODO: len should be bounded for overflow, etc
-    pub fn from_read<T: Read>(src: &mut T) -> Result<FeslMessage, Error> {
+    #[cfg(feature = "std")]
+    pub fn from_read<T: Read>(src: &mut T) -> FeslMessageResult<FeslMessage> {
+        FeslMessage::from_read_with(src, &FeslDecodePolicy::default())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_read_with<T: Read>(src: &mut T, policy: &FeslDecodePolicy) -> FeslMessageResult<FeslMessage> {
         let mut header = [0u8; 12];
         src.read_exact(&mut header)?;
         let len = BigEndian::read_u32(&header[8..12]) as usize;
-        let mut buf: Vec<u8> = Vec::with_capacity(len);
-        buf.extend(&header);
-        src.take((len - 12) as u64).read_to_end(&mut buf)?;
-        Ok(FeslMessage {
-            data: buf.into_boxed_slice()
-        })
+        policy.check_len(len)?;
+        let mut buf: Vec<u8> = vec![0u8; len];
+        buf[..12].copy_from_slice(&header);
+        src.read_exact(&mut buf[12..])?;
+        policy.check_body(&buf[12..])?;
+        Ok(FeslMessage::new(buf.into_boxed_slice()))
     }
 
     pub fn get_cmd(&self) -> Result<&str, str::Utf8Error> {
-        Ok(str::from_utf8(&self.data[0..4])?)
+        self.as_message_ref().get_cmd()
+    }
+
+    pub fn get_command(&self) -> FeslCommand {
+        self.as_message_ref().get_command()
     }
 
     pub fn get_type(&self) -> FeslMessageResult<FeslMessageType> {
-        let val = self.data[4] & 0xf0;
-        FeslMessageType::from_u8(val).ok_or_else(|| FeslMessageError::InvalidType(val))
+        self.as_message_ref().get_type()
+    }
+
+    pub fn get_message_type(&self) -> FeslMessageType {
+        self.as_message_ref().get_message_type()
     }
 
     pub fn get_id(&self) -> u32 {
-        BigEndian::read_u32(&self.data[4..12]) & 0xfffffff
+        self.as_message_ref().get_id()
+    }
+
+    pub fn get_type_and_id(&self) -> u32 {
+        self.as_message_ref().get_type_and_id()
+    }
+
+    pub fn get_len_field(&self) -> u32 {
+        self.as_message_ref().get_len_field()
     }
 
     pub fn as_bytes(&self) -> &[u8] {
         &self.data[..]
     }
+
+    pub fn raw_iter(&self) -> FeslRawIterator<'_> {
+        self.as_message_ref().raw_iter()
+    }
+
+    pub fn as_message_ref(&self) -> FeslMessageRef<'_> {
+        FeslMessageRef {
+            data: &self.data[..]
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct FeslMessageIterator<'a> {
-    src: &'a [u8]
+impl TryFrom<Vec<u8>> for FeslMessage {
+    type Error = Error;
+
+    fn try_from(src: Vec<u8>) -> FeslMessageResult<FeslMessage> {
+        FeslMessageRef::parse_exact(&src)?;
+        Ok(FeslMessage::new(src.into_boxed_slice()))
+    }
 }
 
-impl <'a> FeslMessageIterator<'a> {
-    fn index_of(&self, token: u8) -> Option<usize> {
-        self.src.iter().position(|&x| x == token)
+impl <'a> TryFrom<&'a [u8]> for FeslMessage {
+    type Error = Error;
+
+    fn try_from(src: &'a [u8]) -> FeslMessageResult<FeslMessage> {
+        Ok(FeslMessageRef::parse_exact(src)?.to_message())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FeslMessageRef<'a> {
+    data: &'a [u8]
+}
+
+impl <'a> FeslMessageRef<'a> {
+    // returns `None` until `src` holds the whole message, so a partial receive buffer can be retried
+    pub fn parse(src: &'a [u8]) -> FeslMessageResult<Option<FeslMessageRef<'a>>> {
+        FeslMessageRef::parse_with(src, &FeslDecodePolicy::default())
+    }
+
+    pub fn parse_with(src: &'a [u8], policy: &FeslDecodePolicy) -> FeslMessageResult<Option<FeslMessageRef<'a>>> {
+        Ok(policy.frame_len(src)?.map(|len| FeslMessageRef {
+            data: &src[..len]
+        }))
+    }
+
+    fn parse_exact(src: &'a [u8]) -> FeslMessageResult<FeslMessageRef<'a>> {
+        match FeslMessageRef::parse(src)? {
+            Some(msg) if msg.len() < src.len() => Err(Error::new(ErrorKind::TrailingBytes(src.len() - msg.len())).at(msg.len())),
+            Some(msg) => Ok(msg),
+            None => Err(Error::new(ErrorKind::Incomplete(src.len())).at(src.len()))
+        }
+    }
+
+    pub fn get_cmd(&self) -> Result<&'a str, str::Utf8Error> {
+        str::from_utf8(&self.data[0..4])
+    }
+
+    pub fn get_command(&self) -> FeslCommand {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(&self.data[0..4]);
+        FeslCommand::new(buf)
+    }
+
+    // only the four known types are accepted here; see `get_message_type` for anything else
+    pub fn get_type(&self) -> FeslMessageResult<FeslMessageType> {
+        match FeslMessageType::from_bits(self.data[4]) {
+            FeslMessageType::Other(val) => Err(Error::new(ErrorKind::InvalidType(val)).at(4)),
+            val => Ok(val)
+        }
+    }
+
+    pub fn get_message_type(&self) -> FeslMessageType {
+        FeslMessageType::from_bits(self.data[4])
+    }
+
+    pub fn get_id(&self) -> u32 {
+        self.get_type_and_id() & 0xfffffff
+    }
+
+    pub fn get_type_and_id(&self) -> u32 {
+        BigEndian::read_u32(&self.data[4..8])
+    }
+
+    pub fn get_len_field(&self) -> u32 {
+        BigEndian::read_u32(&self.data[8..12])
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn raw_iter(&self) -> FeslRawIterator<'a> {
+        FeslRawIterator::new(&self.data[12..], 12)
+    }
+
+    pub fn to_message(&self) -> FeslMessage {
+        FeslMessage::new(self.data.to_vec().into_boxed_slice())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FeslDecoder {
+    buf: Vec<u8>,
+    pos: usize,
+    policy: FeslDecodePolicy
+}
+
+impl FeslDecoder {
+    pub fn new() -> FeslDecoder {
+        FeslDecoder::with_policy(FeslDecodePolicy::default())
+    }
+
+    pub fn with_policy(policy: FeslDecodePolicy) -> FeslDecoder {
+        FeslDecoder {
+            buf: Vec::new(),
+            pos: 0,
+            policy
+        }
+    }
+
+    pub fn push(&mut self, src: &[u8]) {
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        self.buf.extend(src);
     }
 
-    fn shift_slice(&mut self, token: u8) -> FeslMessageResult<&'a [u8]> {
-        let x = match self.index_of(token) {
-            Some(x) => x,
-            _ => return Err(FeslMessageError::ExpectedDelimiter)
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    pub fn decode(&mut self) -> FeslMessageResult<Option<FeslMessage>> {
+        let len = match self.policy.frame_len(&self.buf[self.pos..]) {
+            Ok(Some(len)) => len,
+            Ok(None) => return Ok(None),
+            Err(v) => return self.end(v)
         };
-        let val = &self.src[..x];
-        self.src = &self.src[x + 1..];
-        Ok(val)
+        let data = self.buf[self.pos..self.pos + len].to_vec().into_boxed_slice();
+        self.pos += len;
+        Ok(Some(FeslMessage::new(data)))
+    }
+
+    fn end<T>(&mut self, val: Error) -> FeslMessageResult<T> {
+        self.buf.clear();
+        self.pos = 0;
+        Err(val)
+    }
+}
+
+impl Iterator for FeslDecoder {
+    type Item = FeslMessageResult<FeslMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.decode() {
+            Ok(Some(msg)) => Some(Ok(msg)),
+            Ok(None) => None,
+            Err(v) => Some(Err(v))
+        }
     }
+}
 
-    fn shift_str(&mut self, token: u8) -> FeslMessageResult<&'a str> {
-        Ok(str::from_utf8(self.shift_slice(token)?)?)
+#[derive(Debug)]
+pub struct FeslRawIterator<'a> {
+    src: &'a [u8],
+    pos: usize,
+    offset: usize
+}
+
+impl <'a> FeslRawIterator<'a> {
+    fn new(src: &'a [u8], offset: usize) -> FeslRawIterator<'a> {
+        FeslRawIterator {
+            src,
+            pos: 0,
+            offset
+        }
     }
 
-    fn read(&mut self) -> FeslMessageResult<(&'a str, &'a str)> {
-        Ok((&self.shift_str(b'=')?, &self.shift_str(b'\n')?))
+    fn shift(&mut self, end: Option<usize>) -> FeslMessageResult<Range<usize>> {
+        let start = self.pos;
+        match end {
+            Some(x) => {
+                self.pos = start + x + 1;
+                Ok(start..start + x)
+            }
+            _ => Err(Error::new(ErrorKind::ExpectedDelimiter).at(self.offset + start))
+        }
+    }
+
+    fn read(&mut self) -> FeslMessageResult<(Range<usize>, Range<usize>)> {
+        let rest = &self.src[self.pos..];
+        let key = match memchr2(b'=', b'\n', rest) {
+            Some(x) if rest[x] == b'=' => self.shift(Some(x))?,
+            _ => return Err(Error::new(ErrorKind::ExpectedDelimiter).at(self.offset + self.pos))
+        };
+        let value = self.shift(memchr(b'\n', &self.src[self.pos..]))?;
+        Ok((key, value))
+    }
+
+    // ranges are relative to the start of the body
+    fn next_range(&mut self) -> Option<FeslMessageResult<(Range<usize>, Range<usize>)>> {
+        match self.src.len() - self.pos {
+            0 => None,
+            1 if self.src[self.pos] == 0x00 => {
+                self.pos += 1;
+                None
+            }
+            1 => Some(self.end(Error::new(ErrorKind::ExpectedDelimiter).at(self.offset + self.pos))),
+            _ => Some(self.read().or_else(|x| self.end(x)))
+        }
     }
 
     fn end<T, E>(&mut self, val: E) -> Result<T, E> {
-        self.src = &self.src[self.src.len()..];
+        self.pos = self.src.len();
         Err(val)
     }
 }
 
+impl <'a> Iterator for FeslRawIterator<'a> {
+    type Item = FeslMessageResult<(&'a [u8], &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_range()?.map(|(key, value)| (&self.src[key], &self.src[value])))
+    }
+}
+
+#[derive(Debug)]
+pub struct FeslMessageIterator<'a> {
+    raw: FeslRawIterator<'a>,
+    // the body up to its first invalid utf-8 sequence, validated once so fields can be sliced without rechecking
+    text: &'a str,
+    error: Option<str::Utf8Error>
+}
+
+impl <'a> FeslMessageIterator<'a> {
+    fn new(src: &'a [u8], offset: usize) -> FeslMessageIterator<'a> {
+        let (text, error) = match str::from_utf8(src) {
+            Ok(text) => (text, None),
+            Err(error) => (str::from_utf8(&src[..error.valid_up_to()]).unwrap(), Some(error))
+        };
+        FeslMessageIterator {
+            raw: FeslRawIterator::new(src, offset),
+            text,
+            error
+        }
+    }
+
+    // delimiters are ascii, so a field is valid exactly when it ends before the first invalid sequence
+    fn get(&self, range: Range<usize>) -> FeslMessageResult<&'a str> {
+        match self.text.get(range) {
+            Some(val) => Ok(val),
+            None => Err(Error::from(self.error.unwrap()).at(self.raw.offset + self.text.len()))
+        }
+    }
+
+    fn read(&self, key: Range<usize>, value: Range<usize>) -> FeslMessageResult<(&'a str, &'a str)> {
+        let key = self.get(key)?;
+        let value = self.get(value).map_err(|x| x.with_key(key))?;
+        Ok((key, value))
+    }
+}
+
 impl <'a> Iterator for FeslMessageIterator<'a> {
     type Item = FeslMessageResult<(&'a str, &'a str)>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.src.len() {
-            0 => None,
-            1 => match self.shift_slice(0x00).or_else(|x| self.end(x)) {
-                Err(v) => Some(Err(v)),
-                _ => None
-            },
-            _ => Some(self.read().or_else(|x| self.end(x)))
-        }
+        Some(match self.raw.next_range()? {
+            Ok((key, value)) => self.read(key, value).or_else(|x| self.raw.end(x)),
+            Err(v) => Err(v)
+        })
     }
 }
 
@@ -123,43 +518,139 @@ impl <'a> IntoIterator for &'a FeslMessage {
     type IntoIter = FeslMessageIterator<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        FeslMessageIterator {
-            src: &self.data[12..]
-        }
+        self.as_message_ref().into_iter()
+    }
+}
+
+impl <'a> IntoIterator for FeslMessageRef<'a> {
+    type Item = FeslMessageResult<(&'a str, &'a str)>;
+    type IntoIter = FeslMessageIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        FeslMessageIterator::new(&self.data[12..], 12)
     }
 }
 
 #[derive(Debug)]
 pub struct FeslMessageBuilder<'a> {
-    cmd: &'a str,
+    cmd: FeslCommand,
     type_and_id: u32,
     len: usize,
-    buf: Vec<(&'a str, &'a str)>
+    buf: Vec<(Cow<'a, str>, Cow<'a, str>)>
 }
 
+// a builder that owns all of its keys and values and can be passed around freely
+pub type FeslOwnedMessageBuilder = FeslMessageBuilder<'static>;
+
 impl <'a> FeslMessageBuilder<'a> {
-    pub fn new(cmd: &'a str, fesl_type: FeslMessageType, id: u32) -> FeslMessageBuilder<'a> {
-        if cmd.len() != 4 {
-            panic!("FeslMessageBuilder cmd must be a 4 character string");
+    pub fn new(cmd: &str, fesl_type: FeslMessageType, id: u32) -> FeslMessageBuilder<'a> {
+        match FeslMessageBuilder::try_new(cmd, fesl_type, id) {
+            Ok(builder) => builder,
+            Err(_) => panic!("FeslMessageBuilder cmd must be a 4 character string")
         }
+    }
+
+    pub fn try_new(cmd: &str, fesl_type: FeslMessageType, id: u32) -> FeslMessageResult<FeslMessageBuilder<'a>> {
+        Ok(FeslMessageBuilder::with_command(FeslCommand::try_from(cmd)?, fesl_type, id))
+    }
+
+    pub fn with_command(cmd: FeslCommand, fesl_type: FeslMessageType, id: u32) -> FeslMessageBuilder<'a> {
         FeslMessageBuilder {
             cmd,
-            type_and_id: (((fesl_type as u32) & 0xf0) << 24) | (id & 0xfffffff),
+            type_and_id: ((fesl_type.bits() as u32) << 24) | (id & 0xfffffff),
             len: 13,
             buf: Vec::new()
         }
     }
 
-    pub fn push(&mut self, key: &'a str, value: &'a str) {
+    // push takes decoded values, so pairs from iterating a message go through `unescaped()` or `push_raw`
+    // keys containing '=', '\n' or nul can't be written, so push panics on them and try_push reports them
+    pub fn push<K: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>>(&mut self, key: K, value: V) {
+        self.push_cow(key.into(), escape(value))
+    }
+
+    pub fn try_push<K: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>>(&mut self, key: K, value: V) -> FeslMessageResult<()> {
+        self.try_push_cow(key.into(), escape(value))
+    }
+
+    // push_raw takes values already in wire form, exactly as the message iterator yields them
+    pub fn push_raw<K: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>>(&mut self, key: K, value: V) {
+        self.push_cow(key.into(), value.into())
+    }
+
+    pub fn try_push_raw<K: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>>(&mut self, key: K, value: V) -> FeslMessageResult<()> {
+        self.try_push_cow(key.into(), value.into())
+    }
+
+    pub fn push_u32<K: Into<Cow<'a, str>>>(&mut self, key: K, value: u32) {
+        self.push_cow(key.into(), Cow::Owned(value.to_string()))
+    }
+
+    pub fn push_i32<K: Into<Cow<'a, str>>>(&mut self, key: K, value: i32) {
+        self.push_cow(key.into(), Cow::Owned(value.to_string()))
+    }
+
+    pub fn push_u64<K: Into<Cow<'a, str>>>(&mut self, key: K, value: u64) {
+        self.push_cow(key.into(), Cow::Owned(value.to_string()))
+    }
+
+    pub fn push_i64<K: Into<Cow<'a, str>>>(&mut self, key: K, value: i64) {
+        self.push_cow(key.into(), Cow::Owned(value.to_string()))
+    }
+
+    pub fn push_bool<K: Into<Cow<'a, str>>>(&mut self, key: K, value: bool) {
+        self.push_cow(key.into(), Cow::Borrowed(if value { "true" } else { "false" }))
+    }
+
+    fn push_cow(&mut self, key: Cow<'a, str>, value: Cow<'a, str>) {
+        if let Err(err) = self.try_push_cow(key, value) {
+            panic!("FeslMessageBuilder pair is invalid: {}", err)
+        }
+    }
+
+    fn try_push_cow(&mut self, key: Cow<'a, str>, value: Cow<'a, str>) -> FeslMessageResult<()> {
+        escape::check_key(&key)?;
+        escape::check_raw_value(&key, &value)?;
         self.len += key.len() + 1 + value.len() + 1;
-        self.buf.push((key, value))
+        self.buf.push((key, value));
+        Ok(())
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        self.len
+    }
+
+    fn header(&self) -> [u8; 12] {
+        let mut header = [0u8; 12];
+        header[..4].copy_from_slice(self.cmd.as_bytes());
+        BigEndian::write_u32(&mut header[4..8], self.type_and_id);
+        BigEndian::write_u32(&mut header[8..12], self.len as u32);
+        header
+    }
+
+    // writes the header and the pushed keys and values in place, a fixed number of slices per
+    // write_vectored call, so nothing is copied and nothing is allocated however large the message
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, dst: &mut W) -> FeslMessageResult<()> {
+        let header = self.header();
+        let pairs = self.buf.iter().flat_map(|(key, value)| [key.as_bytes(), &b"="[..], value.as_bytes(), &b"\n"[..]]);
+        let mut slices = [IoSlice::new(&[]); 64];
+        let mut len = 0;
+        for src in Some(&header[..]).into_iter().chain(pairs).chain(Some(&[0x00][..])) {
+            slices[len] = IoSlice::new(src);
+            len += 1;
+            if len == slices.len() {
+                write_all_vectored(dst, &mut slices[..len])?;
+                len = 0;
+            }
+        }
+        write_all_vectored(dst, &mut slices[..len])?;
+        Ok(())
     }
 
     pub fn build(self) -> FeslMessage {
         let mut buf: Vec<u8> = Vec::with_capacity(self.len);
-        buf.extend(self.cmd.as_bytes());
-        buf.write_u32::<BigEndian>(self.type_and_id).unwrap();
-        buf.write_u32::<BigEndian>(self.len as u32).unwrap();
+        buf.extend(&self.header());
         for (key, value) in &self.buf {
             buf.extend(key.as_bytes());
             buf.push(b'=');
@@ -167,8 +658,21 @@ impl <'a> FeslMessageBuilder<'a> {
             buf.push(b'\n');
         }
         buf.push(0x00);
-        FeslMessage {
-            data: buf.into_boxed_slice()
+        FeslMessage::new(buf.into_boxed_slice())
+    }
+}
+
+// Write::write_all_vectored is unstable, so partial writes are handled here
+#[cfg(feature = "std")]
+fn write_all_vectored<W: Write>(dst: &mut W, mut slices: &mut [IoSlice]) -> io::Result<()> {
+    IoSlice::advance_slices(&mut slices, 0);
+    while !slices.is_empty() {
+        match dst.write_vectored(slices) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => IoSlice::advance_slices(&mut slices, n),
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err)
         }
     }
+    Ok(())
 }
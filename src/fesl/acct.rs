@@ -0,0 +1,150 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use fesl_codec_derive::FeslTransaction;
+
+// either nuid and password, or the encryptedInfo returned by an earlier login
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuLogin", type = "SingleClient")]
+pub struct NuLogin {
+    #[fesl(rename = "returnEncryptedInfo")]
+    pub return_encrypted_info: Option<bool>,
+    pub nuid: Option<String>,
+    pub password: Option<String>,
+    #[fesl(rename = "encryptedInfo")]
+    pub encrypted_info: Option<String>,
+    #[fesl(rename = "macAddr")]
+    pub mac_addr: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuLogin", type = "SingleServer")]
+pub struct NuLoginResponse {
+    pub lkey: String,
+    pub nuid: String,
+    #[fesl(rename = "profileId")]
+    pub profile_id: u32,
+    #[fesl(rename = "userId")]
+    pub user_id: u32,
+    #[fesl(rename = "displayName")]
+    pub display_name: Option<String>,
+    // only sent when the request asked for it
+    #[fesl(rename = "encryptedLoginInfo")]
+    pub encrypted_login_info: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuLoginPersona", type = "SingleClient")]
+pub struct NuLoginPersona {
+    pub name: String
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuLoginPersona", type = "SingleServer")]
+pub struct NuLoginPersonaResponse {
+    pub lkey: String,
+    #[fesl(rename = "profileId")]
+    pub profile_id: u32,
+    #[fesl(rename = "userId")]
+    pub user_id: u32
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuGetPersonas", type = "SingleClient")]
+pub struct NuGetPersonas {
+    pub namespace: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuGetPersonas", type = "SingleServer")]
+pub struct NuGetPersonasResponse {
+    pub personas: Vec<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuAddPersona", type = "SingleClient")]
+pub struct NuAddPersona {
+    pub name: String
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuAddPersona", type = "SingleServer")]
+pub struct NuAddPersonaResponse {}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuDisablePersona", type = "SingleClient")]
+pub struct NuDisablePersona {
+    pub name: String
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuDisablePersona", type = "SingleServer")]
+pub struct NuDisablePersonaResponse {}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuGetAccount", type = "SingleClient")]
+pub struct NuGetAccount {}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuGetAccount", type = "SingleServer")]
+pub struct NuGetAccountResponse {
+    pub nuid: String,
+    #[fesl(rename = "userId")]
+    pub user_id: u32,
+    #[fesl(rename = "heroName")]
+    pub hero_name: Option<String>,
+    #[fesl(rename = "DOBDay")]
+    pub dob_day: Option<u32>,
+    #[fesl(rename = "DOBMonth")]
+    pub dob_month: Option<u32>,
+    #[fesl(rename = "DOBYear")]
+    pub dob_year: Option<u32>,
+    pub country: Option<String>,
+    pub language: Option<String>,
+    #[fesl(rename = "globalOptin")]
+    pub global_optin: Option<bool>,
+    #[fesl(rename = "thirdPartyOptin")]
+    pub third_party_optin: Option<bool>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuGetTos", type = "SingleClient")]
+pub struct NuGetTos {
+    #[fesl(rename = "countryCode")]
+    pub country_code: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuGetTos", type = "SingleServer")]
+pub struct NuGetTosResponse {
+    pub tos: String,
+    pub version: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuAddAccount", type = "SingleClient")]
+pub struct NuAddAccount {
+    pub nuid: String,
+    pub password: String,
+    #[fesl(rename = "globalOptin")]
+    pub global_optin: Option<bool>,
+    #[fesl(rename = "thirdPartyOptin")]
+    pub third_party_optin: Option<bool>,
+    #[fesl(rename = "parentalEmail")]
+    pub parental_email: Option<String>,
+    #[fesl(rename = "DOBDay")]
+    pub dob_day: u32,
+    #[fesl(rename = "DOBMonth")]
+    pub dob_month: u32,
+    #[fesl(rename = "DOBYear")]
+    pub dob_year: u32,
+    #[fesl(rename = "zipCode")]
+    pub zip_code: Option<String>,
+    pub country: String,
+    pub language: Option<String>,
+    #[fesl(rename = "tosVersion")]
+    pub tos_version: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "acct", txn = "NuAddAccount", type = "SingleServer")]
+pub struct NuAddAccountResponse {}
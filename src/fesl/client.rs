@@ -0,0 +1,194 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use std::io::{Read, Write};
+use error::{Error, ErrorKind};
+use super::{FeslCommand, FeslDecodePolicy, FeslFragmentAssembler, FeslFragmentPolicy, FeslMessage, FeslMessageBuilder, FeslMessageResult, FeslTransaction};
+
+#[derive(Debug)]
+struct FeslPendingRequest {
+    cmd: FeslCommand,
+    txn: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeslClientPolicy {
+    pub decode: FeslDecodePolicy,
+    pub fragments: FeslFragmentPolicy,
+    // requests sent but not yet taken with `wait`; sending more is an error
+    pub max_pending: usize,
+    // server pushes and orphans each keep at most this many, dropping the oldest
+    pub max_queued: usize
+}
+
+impl Default for FeslClientPolicy {
+    fn default() -> Self {
+        FeslClientPolicy {
+            decode: FeslDecodePolicy::default(),
+            fragments: FeslFragmentPolicy::default(),
+            max_pending: 256,
+            max_queued: 64
+        }
+    }
+}
+
+// a blocking client that pairs replies with requests by id; id 0 is reserved for server pushes
+#[derive(Debug)]
+pub struct FeslClient<T> {
+    transport: T,
+    policy: FeslClientPolicy,
+    assembler: FeslFragmentAssembler,
+    next_id: u32,
+    pending: BTreeMap<u32, FeslPendingRequest>,
+    ready: BTreeMap<u32, FeslMessageResult<FeslMessage>>,
+    unsolicited: VecDeque<FeslMessage>,
+    orphans: VecDeque<FeslMessage>
+}
+
+impl <T: Read + Write> FeslClient<T> {
+    pub fn new(transport: T) -> FeslClient<T> {
+        FeslClient::with_policy(transport, FeslClientPolicy::default())
+    }
+
+    pub fn with_policy(transport: T, policy: FeslClientPolicy) -> FeslClient<T> {
+        FeslClient {
+            transport,
+            assembler: FeslFragmentAssembler::with_policy(policy.decode.clone(), policy.fragments.clone()),
+            policy,
+            next_id: 1,
+            pending: BTreeMap::new(),
+            ready: BTreeMap::new(),
+            unsolicited: VecDeque::new(),
+            orphans: VecDeque::new()
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn assign_id(&mut self) -> u32 {
+        loop {
+            let id = self.next_id;
+            self.next_id = if id >= 0xfffffff { 1 } else { id + 1 };
+            if !self.pending.contains_key(&id) && !self.ready.contains_key(&id) {
+                return id;
+            }
+        }
+    }
+
+    // overwrites the builder's id with a fresh one, which is returned for `wait`
+    pub fn send(&mut self, mut builder: FeslMessageBuilder) -> FeslMessageResult<u32> {
+        let outstanding = self.pending.len() + self.ready.len();
+        if outstanding >= self.policy.max_pending {
+            return Err(ErrorKind::TooManyPending(outstanding).into());
+        }
+        let id = self.assign_id();
+        builder.type_and_id = (builder.type_and_id & 0xf0000000) | id;
+        let txn = builder.buf.iter().find(|x| x.0 == "TXN").map(|x| x.1.to_string());
+        builder.write_to(&mut self.transport)?;
+        self.transport.flush()?;
+        self.pending.insert(id, FeslPendingRequest {
+            cmd: builder.cmd,
+            txn
+        });
+        Ok(id)
+    }
+
+    pub fn send_transaction<R: FeslTransaction>(&mut self, request: &R) -> FeslMessageResult<u32> {
+        let mut builder = FeslMessageBuilder::with_command(R::CMD, R::TYPE, 0);
+        builder.extend(request.to_value().flatten());
+        self.send(builder)
+    }
+
+    pub fn wait(&mut self, id: u32) -> FeslMessageResult<FeslMessage> {
+        loop {
+            if let Some(reply) = self.ready.remove(&id) {
+                return reply;
+            }
+            if !self.pending.contains_key(&id) {
+                return Err(ErrorKind::UnknownRequest(id).into());
+            }
+            self.recv()?;
+        }
+    }
+
+    pub fn call(&mut self, builder: FeslMessageBuilder) -> FeslMessageResult<FeslMessage> {
+        let id = self.send(builder)?;
+        self.wait(id)
+    }
+
+    pub fn call_transaction<R: FeslTransaction, S: FeslTransaction>(&mut self, request: &R) -> FeslMessageResult<S> {
+        let id = self.send_transaction(request)?;
+        S::from_message(&self.wait(id)?)
+    }
+
+    // reads until a push with id 0 arrives, resolving any replies that come in first
+    pub fn next_unsolicited(&mut self) -> FeslMessageResult<FeslMessage> {
+        loop {
+            if let Some(msg) = self.unsolicited.pop_front() {
+                return Ok(msg);
+            }
+            self.recv()?;
+        }
+    }
+
+    pub fn try_unsolicited(&mut self) -> Option<FeslMessage> {
+        self.unsolicited.pop_front()
+    }
+
+    // replies whose id was never requested or has already been resolved
+    pub fn next_orphan(&mut self) -> Option<FeslMessage> {
+        self.orphans.pop_front()
+    }
+
+    // reads one message from the transport and files it as a reply, push or orphan
+    pub fn recv(&mut self) -> FeslMessageResult<()> {
+        let msg = FeslMessage::from_read_with(&mut self.transport, &self.policy.decode)?;
+        let msg = match self.assembler.push(msg)? {
+            Some(msg) => msg,
+            None => return Ok(())
+        };
+        let id = msg.get_id();
+        if id == 0 {
+            push_bounded(&mut self.unsolicited, msg, self.policy.max_queued);
+            return Ok(());
+        }
+        let request = match self.pending.remove(&id) {
+            Some(request) => request,
+            None => {
+                push_bounded(&mut self.orphans, msg, self.policy.max_queued);
+                return Ok(());
+            }
+        };
+        let txn = request.txn.as_ref().map(|x| &x[..]);
+        let reply = if msg.get_command() != request.cmd || (txn.is_some() && msg.get_raw("TXN").ok().flatten() != txn) {
+            Err(Error::new(ErrorKind::MismatchedReply(id)))
+        } else {
+            Ok(msg)
+        };
+        self.ready.insert(id, reply);
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+}
+
+fn push_bounded(queue: &mut VecDeque<FeslMessage>, msg: FeslMessage, max: usize) {
+    if max == 0 {
+        return;
+    }
+    if queue.len() >= max {
+        queue.pop_front();
+    }
+    queue.push_back(msg);
+}
@@ -0,0 +1,77 @@
+use std::io;
+use bytes::{BufMut, BytesMut};
+use super::byteorder::{ByteOrder, BigEndian};
+use tokio_util::codec::{Decoder, Encoder};
+use error::Error;
+use super::{FeslDecodePolicy, FeslMessage, FeslMessageBuilder};
+
+#[derive(Debug, Default)]
+pub struct FeslCodec {
+    policy: FeslDecodePolicy
+}
+
+impl FeslCodec {
+    pub fn new() -> FeslCodec {
+        FeslCodec::with_policy(FeslDecodePolicy::default())
+    }
+
+    pub fn with_policy(policy: FeslDecodePolicy) -> FeslCodec {
+        FeslCodec {
+            policy
+        }
+    }
+}
+
+impl Decoder for FeslCodec {
+    type Item = FeslMessage;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<FeslMessage>, Error> {
+        match self.policy.frame_len(&src[..])? {
+            Some(len) => Ok(Some(FeslMessage::new(src.split_to(len).to_vec().into_boxed_slice()))),
+            None => {
+                // header is validated by now, so the full frame can be reserved up front
+                if src.len() >= 12 {
+                    let len = BigEndian::read_u32(&src[8..12]) as usize;
+                    src.reserve(len - src.len());
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<FeslMessage>, Error> {
+        match self.decode(src)? {
+            Some(msg) => Ok(Some(msg)),
+            None if src.is_empty() => Ok(None),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "bytes remaining on stream").into())
+        }
+    }
+}
+
+impl Encoder<FeslMessage> for FeslCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: FeslMessage, dst: &mut BytesMut) -> Result<(), Error> {
+        dst.extend_from_slice(item.as_bytes());
+        Ok(())
+    }
+}
+
+impl <'a> Encoder<&'a FeslMessage> for FeslCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: &'a FeslMessage, dst: &mut BytesMut) -> Result<(), Error> {
+        dst.extend_from_slice(item.as_bytes());
+        Ok(())
+    }
+}
+
+impl <'a> Encoder<FeslMessageBuilder<'a>> for FeslCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: FeslMessageBuilder<'a>, dst: &mut BytesMut) -> Result<(), Error> {
+        dst.reserve(item.encoded_len());
+        item.write_to(&mut dst.writer())
+    }
+}
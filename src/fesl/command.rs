@@ -0,0 +1,72 @@
+use core::convert::TryFrom;
+use core::fmt;
+use core::str;
+use alloc::string::String;
+use error::{Error, ErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FeslCommand([u8; 4]);
+
+impl FeslCommand {
+    pub const FSYS: FeslCommand = FeslCommand::new(*b"fsys");
+    pub const ACCT: FeslCommand = FeslCommand::new(*b"acct");
+    pub const SUBS: FeslCommand = FeslCommand::new(*b"subs");
+    pub const DOBJ: FeslCommand = FeslCommand::new(*b"dobj");
+    pub const RANK: FeslCommand = FeslCommand::new(*b"rank");
+    pub const PRES: FeslCommand = FeslCommand::new(*b"pres");
+    pub const RECP: FeslCommand = FeslCommand::new(*b"recp");
+    pub const ASSO: FeslCommand = FeslCommand::new(*b"asso");
+    pub const XMSG: FeslCommand = FeslCommand::new(*b"xmsg");
+    pub const PNOW: FeslCommand = FeslCommand::new(*b"pnow");
+    pub const FLTR: FeslCommand = FeslCommand::new(*b"fltr");
+
+    // any 4 bytes are accepted, since that is all the header guarantees
+    pub const fn new(cmd: [u8; 4]) -> FeslCommand {
+        FeslCommand(cmd)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+
+    pub fn as_str(&self) -> Result<&str, str::Utf8Error> {
+        str::from_utf8(&self.0)
+    }
+}
+
+impl <'a> TryFrom<&'a str> for FeslCommand {
+    type Error = Error;
+
+    fn try_from(cmd: &'a str) -> Result<FeslCommand, Error> {
+        let mut buf = [0u8; 4];
+        if cmd.len() != buf.len() {
+            return Err(ErrorKind::InvalidCommandLength.into());
+        }
+        buf.copy_from_slice(cmd.as_bytes());
+        Ok(FeslCommand(buf))
+    }
+}
+
+impl From<[u8; 4]> for FeslCommand {
+    fn from(cmd: [u8; 4]) -> FeslCommand {
+        FeslCommand(cmd)
+    }
+}
+
+impl PartialEq<str> for FeslCommand {
+    fn eq(&self, other: &str) -> bool {
+        &self.0[..] == other.as_bytes()
+    }
+}
+
+impl <'a> PartialEq<&'a str> for FeslCommand {
+    fn eq(&self, other: &&'a str) -> bool {
+        &self.0[..] == other.as_bytes()
+    }
+}
+
+impl fmt::Display for FeslCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.0))
+    }
+}
@@ -0,0 +1,84 @@
+use core::mem;
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use super::byteorder::{ByteOrder, BigEndian};
+use super::escape::{check_key, check_raw_value};
+use super::{escape, FeslMessage, FeslMessageResult, FeslMessageType};
+
+impl FeslMessage {
+    // replaces the first occurrence of `key`, or appends it before the terminator
+    pub fn set<'a, V: Into<Cow<'a, str>>>(&mut self, key: &str, value: V) -> FeslMessageResult<()> {
+        self.set_raw(key, escape(value))
+    }
+
+    pub fn set_raw<'a, V: Into<Cow<'a, str>>>(&mut self, key: &str, value: V) -> FeslMessageResult<()> {
+        let value = value.into();
+        check_key(key)?;
+        check_raw_value(key, &value)?;
+        let edit = match self.lookup(key)?.first() {
+            Some(x) => (x.2, x.3, value.as_bytes().to_vec()),
+            None => {
+                let end = self.body_end();
+                (end, end, format!("{}={}\n", key, value).into_bytes())
+            }
+        };
+        self.splice(vec![edit]);
+        Ok(())
+    }
+
+    // removes every occurrence of `key`, returning whether there was one
+    pub fn remove(&mut self, key: &str) -> FeslMessageResult<bool> {
+        let edits: Vec<_> = self.lookup(key)?.iter().map(|x| (x.0, x.3 + 1, Vec::new())).collect();
+        let found = !edits.is_empty();
+        self.splice(edits);
+        Ok(found)
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) -> FeslMessageResult<bool> {
+        check_key(to)?;
+        let edits: Vec<_> = self.lookup(from)?.iter().map(|x| (x.0, x.1, to.as_bytes().to_vec())).collect();
+        let found = !edits.is_empty();
+        self.splice(edits);
+        Ok(found)
+    }
+
+    pub fn set_id(&mut self, id: u32) {
+        let type_and_id = (self.get_type_and_id() & 0xf0000000) | (id & 0xfffffff);
+        self.set_type_and_id(type_and_id);
+    }
+
+    pub fn set_type(&mut self, fesl_type: FeslMessageType) {
+        self.data[4] = fesl_type.bits() | (self.data[4] & 0x0f);
+    }
+
+    pub fn set_type_and_id(&mut self, type_and_id: u32) {
+        BigEndian::write_u32(&mut self.data[4..8], type_and_id);
+    }
+
+    // written as given, even if it no longer matches the message, so malformed traffic can be replayed
+    pub fn set_len_field(&mut self, len: u32) {
+        BigEndian::write_u32(&mut self.data[8..12], len);
+    }
+
+    fn body_end(&self) -> usize {
+        match self.data.last() {
+            Some(0x00) if self.data.len() > 12 => self.data.len() - 1,
+            _ => self.data.len()
+        }
+    }
+
+    // `edits` must not overlap and are given in message order
+    fn splice(&mut self, edits: Vec<(usize, usize, Vec<u8>)>) {
+        if edits.is_empty() {
+            return;
+        }
+        let mut data = mem::take(&mut self.data).into_vec();
+        for (start, end, replacement) in edits.into_iter().rev() {
+            data.splice(start..end, replacement);
+        }
+        let len = data.len() as u32;
+        BigEndian::write_u32(&mut data[8..12], len);
+        self.data = data.into_boxed_slice();
+        self.index = Default::default();
+    }
+}
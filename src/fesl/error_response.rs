@@ -0,0 +1,143 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use fesl_codec_derive::FeslField;
+use super::{FeslCommand, FeslMessage, FeslMessageBuilder, FeslMessageResult, FeslMessageType, FeslValue};
+use super::transaction::FeslField;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeslErrorCode {
+    FieldInvalid,
+    SystemError,
+    UserNotFound,
+    AccountDisabled,
+    InvalidPassword,
+    PersonaExists,
+    Other(u32)
+}
+
+impl FeslErrorCode {
+    pub fn from_code(code: u32) -> FeslErrorCode {
+        match code {
+            21 => FeslErrorCode::FieldInvalid,
+            99 => FeslErrorCode::SystemError,
+            101 => FeslErrorCode::UserNotFound,
+            102 => FeslErrorCode::AccountDisabled,
+            122 => FeslErrorCode::InvalidPassword,
+            160 => FeslErrorCode::PersonaExists,
+            code => FeslErrorCode::Other(code)
+        }
+    }
+
+    pub fn code(self) -> u32 {
+        match self {
+            FeslErrorCode::FieldInvalid => 21,
+            FeslErrorCode::SystemError => 99,
+            FeslErrorCode::UserNotFound => 101,
+            FeslErrorCode::AccountDisabled => 102,
+            FeslErrorCode::InvalidPassword => 122,
+            FeslErrorCode::PersonaExists => 160,
+            FeslErrorCode::Other(code) => code
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            FeslErrorCode::FieldInvalid => "The required parameters for this call are missing or invalid",
+            FeslErrorCode::SystemError => "System Error",
+            FeslErrorCode::UserNotFound => "The user was not found",
+            FeslErrorCode::AccountDisabled => "The account has been disabled",
+            FeslErrorCode::InvalidPassword => "The password the user specified is incorrect",
+            FeslErrorCode::PersonaExists => "That account name is already taken",
+            FeslErrorCode::Other(_) => "Error"
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, FeslField)]
+pub struct FeslFieldError {
+    #[fesl(rename = "fieldName")]
+    pub field_name: String,
+    #[fesl(rename = "fieldError")]
+    pub field_error: u32,
+    pub value: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeslErrorResponse {
+    pub code: FeslErrorCode,
+    pub message: String,
+    pub fields: Vec<FeslFieldError>
+}
+
+impl FeslErrorResponse {
+    pub fn new(code: FeslErrorCode) -> FeslErrorResponse {
+        FeslErrorResponse {
+            code,
+            message: code.message().to_string(),
+            fields: Vec::new()
+        }
+    }
+
+    pub fn with_message(mut self, message: &str) -> FeslErrorResponse {
+        self.message = message.to_string();
+        self
+    }
+
+    pub fn with_field(mut self, field_name: &str, field_error: u32, value: Option<&str>) -> FeslErrorResponse {
+        self.fields.push(FeslFieldError {
+            field_name: field_name.to_string(),
+            field_error,
+            value: value.map(|x| x.to_string())
+        });
+        self
+    }
+
+    // answers `request` with its command, TXN and id
+    pub fn to_reply(&self, request: &FeslMessage) -> FeslMessageResult<FeslMessage> {
+        let fesl_type = request.get_message_type().reply();
+        Ok(self.to_message(request.get_command(), fesl_type, &request.require("TXN")?, request.get_id()))
+    }
+
+    pub fn to_message(&self, cmd: FeslCommand, fesl_type: FeslMessageType, txn: &str, id: u32) -> FeslMessage {
+        self.to_builder(cmd, fesl_type, txn, id).build()
+    }
+
+    // the builder owns its values, so it can outlive this response and be written or fragmented later
+    pub fn to_builder<'a, T: Into<Cow<'a, str>>>(&self, cmd: FeslCommand, fesl_type: FeslMessageType, txn: T, id: u32) -> FeslMessageBuilder<'a> {
+        let mut builder = FeslMessageBuilder::with_command(cmd, fesl_type, id);
+        builder.push("TXN", txn);
+        builder.push("localizedMessage", self.message.clone());
+        builder.push_u32("errorContainer.[]", self.fields.len() as u32);
+        for (i, field) in self.fields.iter().enumerate() {
+            builder.push(format!("errorContainer.{}.fieldName", i), field.field_name.clone());
+            builder.push_u32(format!("errorContainer.{}.fieldError", i), field.field_error);
+            if let Some(ref value) = field.value {
+                builder.push(format!("errorContainer.{}.value", i), value.clone());
+            }
+        }
+        builder.push_u32("errorCode", self.code.code());
+        builder
+    }
+
+    // returns `None` for replies without an errorCode, i.e. successful ones
+    pub fn from_message(msg: &FeslMessage) -> FeslMessageResult<Option<FeslErrorResponse>> {
+        let code = match msg.get_u32("errorCode")? {
+            Some(code) => FeslErrorCode::from_code(code),
+            None => return Ok(None)
+        };
+        let message = match msg.get("localizedMessage")? {
+            Some(message) => message.into_owned(),
+            None => code.message().to_string()
+        };
+        let fields = match msg.get_raw("errorContainer.[]")? {
+            Some(_) => Vec::from_value("errorContainer", FeslValue::from_message(msg)?.get("errorContainer"))?,
+            None => Vec::new()
+        };
+        Ok(Some(FeslErrorResponse {
+            code,
+            message,
+            fields
+        }))
+    }
+}
@@ -0,0 +1,116 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use error::{Error, ErrorKind};
+use super::{FeslMessageBuilder, FeslMessageIterator, FeslMessageResult};
+
+fn needs_escape(x: u8) -> bool {
+    !(0x20..0x80).contains(&x) || x == b'"' || x == b'%' || x == b'='
+}
+
+fn hex_value(x: u8) -> Option<u8> {
+    match x {
+        b'0'..=b'9' => Some(x - b'0'),
+        b'a'..=b'f' => Some(x - b'a' + 10),
+        b'A'..=b'F' => Some(x - b'A' + 10),
+        _ => None
+    }
+}
+
+fn is_key_delimiter(x: u8) -> bool {
+    x == b'=' || x == b'\n' || x == 0x00
+}
+
+// keys and raw values go into the body as they are, so delimiters there would corrupt it
+pub(super) fn check_key(key: &str) -> FeslMessageResult<()> {
+    if key.bytes().any(is_key_delimiter) {
+        return Err(ErrorKind::InvalidKey(key.into()).into());
+    }
+    Ok(())
+}
+
+pub(super) fn check_raw_value(key: &str, value: &str) -> FeslMessageResult<()> {
+    if value.bytes().any(|x| x == b'\n' || x == 0x00) {
+        return Err(Error::new(ErrorKind::InvalidValue(value.into())).with_key(key));
+    }
+    Ok(())
+}
+
+// values with spaces or special bytes are sent quoted, with the special bytes as %xx
+pub fn escape<'a, T: Into<Cow<'a, str>>>(value: T) -> Cow<'a, str> {
+    let value = value.into();
+    if !value.bytes().any(|x| x == b' ' || needs_escape(x)) {
+        return value;
+    }
+    let mut buf = String::with_capacity(value.len() + 2);
+    buf.push('"');
+    for x in value.bytes() {
+        if needs_escape(x) {
+            buf.push_str(&format!("%{:02x}", x));
+        } else {
+            buf.push(x as char);
+        }
+    }
+    buf.push('"');
+    Cow::Owned(buf)
+}
+
+pub fn unescape(value: &str) -> FeslMessageResult<Cow<'_, str>> {
+    let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    };
+    if !value.contains('%') {
+        return Ok(Cow::Borrowed(value));
+    }
+    let src = value.as_bytes();
+    let mut buf: Vec<u8> = Vec::with_capacity(src.len());
+    let mut i = 0;
+    while i < src.len() {
+        // a lone % is kept verbatim rather than rejecting the value
+        if src[i] == b'%' && i + 2 < src.len() {
+            if let (Some(hi), Some(lo)) = (hex_value(src[i + 1]), hex_value(src[i + 2])) {
+                buf.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+        buf.push(src[i]);
+        i += 1;
+    }
+    String::from_utf8(buf).map(Cow::Owned).map_err(|x| Error::from(x.utf8_error()))
+}
+
+#[derive(Debug)]
+pub struct FeslUnescapedIterator<'a> {
+    iter: FeslMessageIterator<'a>
+}
+
+impl <'a> FeslMessageIterator<'a> {
+    pub fn unescaped(self) -> FeslUnescapedIterator<'a> {
+        FeslUnescapedIterator {
+            iter: self
+        }
+    }
+}
+
+impl <'a> Iterator for FeslUnescapedIterator<'a> {
+    type Item = FeslMessageResult<(&'a str, Cow<'a, str>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(match self.iter.next()? {
+            Ok((key, value)) => unescape(value).map(|value| (key, value)),
+            Err(v) => Err(v)
+        })
+    }
+}
+
+// pushes pairs from `unescaped()` so iterating a message and rebuilding it round-trips
+impl <'a> Extend<(&'a str, Cow<'a, str>)> for FeslMessageBuilder<'a> {
+    fn extend<T: IntoIterator<Item = (&'a str, Cow<'a, str>)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.push(key, value);
+        }
+    }
+}
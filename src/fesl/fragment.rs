@@ -0,0 +1,192 @@
+use core::str;
+use alloc::collections::BTreeMap;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use super::byteorder::{ByteOrder, BigEndian};
+use error::{Error, ErrorKind};
+use super::{unescape, FeslDecodePolicy, FeslMessage, FeslMessageBuilder, FeslMessageResult, FeslMessageType};
+
+#[derive(Debug)]
+struct FeslFragments {
+    header: [u8; 8],
+    size: usize,
+    decoded_size: usize,
+    data: Vec<u8>
+}
+
+// limits on the transactions an assembler holds at once, separate from the per message decode policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeslFragmentPolicy {
+    pub max_pending: usize,
+    pub max_buffered: usize
+}
+
+impl Default for FeslFragmentPolicy {
+    fn default() -> Self {
+        FeslFragmentPolicy {
+            max_pending: 64,
+            max_buffered: 0x400000
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FeslFragmentAssembler {
+    pending: BTreeMap<u32, FeslFragments>,
+    buffered: usize,
+    policy: FeslDecodePolicy,
+    fragment_policy: FeslFragmentPolicy
+}
+
+impl FeslFragmentAssembler {
+    pub fn new() -> FeslFragmentAssembler {
+        FeslFragmentAssembler::with_policy(FeslDecodePolicy::default(), FeslFragmentPolicy::default())
+    }
+
+    // `policy` bounds each reassembled message, `fragment_policy` everything still being collected
+    pub fn with_policy(policy: FeslDecodePolicy, fragment_policy: FeslFragmentPolicy) -> FeslFragmentAssembler {
+        FeslFragmentAssembler {
+            pending: BTreeMap::new(),
+            buffered: 0,
+            policy,
+            fragment_policy
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn push(&mut self, msg: FeslMessage) -> FeslMessageResult<Option<FeslMessage>> {
+        let single = match msg.get_message_type() {
+            FeslMessageType::MultiClient => FeslMessageType::SingleClient,
+            FeslMessageType::MultiServer => FeslMessageType::SingleServer,
+            _ => return Ok(Some(msg))
+        };
+        let id = msg.get_id();
+        let result = self.append(id, &msg);
+        if result.is_err() {
+            self.remove(id);
+        }
+        if !result? {
+            return Ok(None);
+        }
+        let fragments = self.remove(id).unwrap();
+        let decoded = STANDARD.decode(&fragments.data).map_err(Error::from)?;
+        if decoded.len() != fragments.decoded_size {
+            return Err(ErrorKind::FragmentSizeMismatch(fragments.decoded_size, decoded.len()).into());
+        }
+        let terminated = decoded.last() == Some(&0x00);
+        let len = 12 + decoded.len() + if terminated { 0 } else { 1 };
+        let mut buf: Vec<u8> = Vec::with_capacity(len);
+        buf.extend(&fragments.header);
+        buf[4] = (buf[4] & 0x0f) | single.bits();
+        buf.extend(&[0u8; 4]);
+        BigEndian::write_u32(&mut buf[8..12], len as u32);
+        buf.extend(decoded);
+        if !terminated {
+            buf.push(0x00);
+        }
+        Ok(Some(FeslMessage::new(buf.into_boxed_slice())))
+    }
+
+    // returns whether the transaction has received all of its encoded data
+    fn append(&mut self, id: u32, msg: &FeslMessage) -> FeslMessageResult<bool> {
+        let mut size = None;
+        let mut decoded_size = None;
+        let mut data = None;
+        for item in msg {
+            match item? {
+                ("size", value) => size = value.parse::<usize>().ok(),
+                ("decodedSize", value) => decoded_size = value.parse::<usize>().ok(),
+                ("data", value) => data = Some(value),
+                _ => ()
+            }
+        }
+        let (size, decoded_size, data) = match (size, decoded_size, data) {
+            (Some(size), Some(decoded_size), Some(data)) => (size, decoded_size, data),
+            _ => return Err(ErrorKind::InvalidFragment.into())
+        };
+        self.policy.check_len(decoded_size.saturating_add(12 + 1))?;
+        // size is the base64 length of decodedSize, so both are bounded by the policy before allocating
+        if size != decoded_size.div_ceil(3) * 4 {
+            return Err(ErrorKind::InvalidFragment.into());
+        }
+        if !self.pending.contains_key(&id) {
+            if self.pending.len() >= self.fragment_policy.max_pending {
+                return Err(ErrorKind::TooManyPending(self.pending.len()).into());
+            }
+            if self.buffered + size > self.fragment_policy.max_buffered {
+                return Err(ErrorKind::MessageTooLarge(self.buffered + size).into());
+            }
+            let mut header = [0u8; 8];
+            header.copy_from_slice(&msg.as_bytes()[..8]);
+            self.pending.insert(id, FeslFragments {
+                header,
+                size,
+                decoded_size,
+                data: Vec::with_capacity(size)
+            });
+            self.buffered += size;
+        }
+        let fragments = self.pending.get_mut(&id).unwrap();
+        if fragments.size != size || fragments.decoded_size != decoded_size || fragments.header[..4] != msg.as_bytes()[..4] {
+            return Err(ErrorKind::InvalidFragment.into());
+        }
+        // real clients escape the base64 padding as %3d
+        fragments.data.extend(unescape(data)?.as_bytes());
+        if fragments.data.len() > size {
+            return Err(ErrorKind::FragmentSizeMismatch(size, fragments.data.len()).into());
+        }
+        Ok(fragments.data.len() == size)
+    }
+
+    fn remove(&mut self, id: u32) -> Option<FeslFragments> {
+        let fragments = self.pending.remove(&id)?;
+        self.buffered -= fragments.size;
+        Some(fragments)
+    }
+}
+
+impl <'a> FeslMessageBuilder<'a> {
+    // only Single* messages can be split, since their Multi* counterparts are what carry the fragments
+    pub fn build_fragments(self, fragment_size: usize) -> FeslMessageResult<Vec<FeslMessage>> {
+        let bits = (self.type_and_id >> 24) as u8;
+        let multi = match FeslMessageType::from_bits(bits) {
+            FeslMessageType::SingleClient => FeslMessageType::MultiClient,
+            FeslMessageType::SingleServer => FeslMessageType::MultiServer,
+            _ => return Err(ErrorKind::InvalidType(bits & 0xf0).into())
+        };
+        if self.len <= fragment_size {
+            return Ok(vec![self.build()]);
+        }
+        let cmd = self.cmd;
+        let type_and_id = ((multi.bits() as u32) << 24) | (self.type_and_id & 0xfffffff);
+        let msg = self.build();
+        let body = &msg.as_bytes()[12..msg.as_bytes().len() - 1];
+        let encoded = STANDARD.encode(body);
+        let size = encoded.len().to_string();
+        let decoded_size = body.len().to_string();
+        // each '=' of padding is sent as %3d, two bytes longer
+        let padding = encoded.bytes().rev().take_while(|&x| x == b'=').count();
+        let overhead = 12 + "decodedSize=\n".len() + decoded_size.len() + "size=\n".len() + size.len() + "data=\n".len() + 1 + padding * 2;
+        if fragment_size <= overhead {
+            return Err(ErrorKind::FragmentSizeTooSmall(fragment_size).into());
+        }
+        // base64 output is ascii, so any byte offset is a valid chunk boundary
+        Ok(encoded.as_bytes().chunks(fragment_size - overhead).map(|chunk| {
+            let mut builder = FeslMessageBuilder {
+                cmd,
+                type_and_id,
+                len: 13,
+                buf: Vec::new()
+            };
+            builder.push("decodedSize", &decoded_size);
+            builder.push("size", &size);
+            builder.push_raw("data", str::from_utf8(chunk).unwrap().replace('=', "%3d"));
+            builder.build()
+        }).collect())
+    }
+}
@@ -0,0 +1,109 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use fesl_codec_derive::{FeslField, FeslTransaction};
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "fsys", txn = "Hello", type = "SingleClient")]
+pub struct Hello {
+    #[fesl(rename = "clientString")]
+    pub client_string: String,
+    pub sku: String,
+    pub locale: String,
+    #[fesl(rename = "clientPlatform")]
+    pub client_platform: String,
+    #[fesl(rename = "clientVersion")]
+    pub client_version: String,
+    #[fesl(rename = "SDKVersion")]
+    pub sdk_version: String,
+    #[fesl(rename = "protocolVersion")]
+    pub protocol_version: String,
+    #[fesl(rename = "fragmentSize")]
+    pub fragment_size: u32,
+    #[fesl(rename = "clientType")]
+    pub client_type: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslField)]
+pub struct DomainPartition {
+    pub domain: String,
+    #[fesl(rename = "subDomain")]
+    pub sub_domain: String
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "fsys", txn = "Hello", type = "SingleServer")]
+pub struct HelloResponse {
+    #[fesl(rename = "domainPartition")]
+    pub domain_partition: DomainPartition,
+    // sent as text, e.g. "Oct-18-2026 12:00:00 UTC"
+    #[fesl(rename = "curTime")]
+    pub cur_time: String,
+    #[fesl(rename = "activityTimeoutSecs")]
+    pub activity_timeout_secs: u32,
+    #[fesl(rename = "messengerIp")]
+    pub messenger_ip: Option<String>,
+    #[fesl(rename = "messengerPort")]
+    pub messenger_port: Option<u16>,
+    #[fesl(rename = "theaterIp")]
+    pub theater_ip: String,
+    #[fesl(rename = "theaterPort")]
+    pub theater_port: u16
+}
+
+#[derive(Debug, Clone, PartialEq, FeslField)]
+pub struct MemCheckEntry {
+    pub addr: String,
+    pub len: u32
+}
+
+// sent by the server, usually with an empty list; the client answers with MemCheckResponse
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "fsys", txn = "MemCheck", type = "SingleServer")]
+pub struct MemCheck {
+    pub memcheck: Vec<MemCheckEntry>,
+    #[fesl(rename = "type")]
+    pub check_type: u32,
+    pub salt: u32
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "fsys", txn = "MemCheck", type = "SingleClient")]
+pub struct MemCheckResponse {
+    pub result: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "fsys", txn = "Ping", type = "SingleServer")]
+pub struct Ping {}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "fsys", txn = "Ping", type = "SingleClient")]
+pub struct PingResponse {}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "fsys", txn = "Goodbye", type = "SingleClient")]
+pub struct Goodbye {
+    pub reason: String,
+    pub message: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "fsys", txn = "GetPingSites", type = "SingleClient")]
+pub struct GetPingSites {}
+
+#[derive(Debug, Clone, PartialEq, FeslField)]
+pub struct PingSite {
+    pub addr: String,
+    pub name: String,
+    #[fesl(rename = "type")]
+    pub site_type: u32
+}
+
+#[derive(Debug, Clone, PartialEq, FeslTransaction)]
+#[fesl(cmd = "fsys", txn = "GetPingSites", type = "SingleServer")]
+pub struct GetPingSitesResponse {
+    #[fesl(rename = "minPingSitesToPing")]
+    pub min_ping_sites_to_ping: u32,
+    #[fesl(rename = "pingSite")]
+    pub ping_sites: Vec<PingSite>
+}
@@ -0,0 +1,137 @@
+use core::str;
+use core::str::FromStr;
+use alloc::borrow::Cow;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use error::{Error, ErrorKind};
+use super::{unescape, FeslMessage, FeslMessageResult};
+
+// byte ranges into the message data, sorted by key so lookups can binary search
+#[derive(Debug, Default)]
+pub struct FeslMessageIndex {
+    entries: Vec<(usize, usize, usize, usize)>,
+    complete: bool
+}
+
+impl FeslMessageIndex {
+    // built from byte ranges so values that are not UTF-8 don't hide the keys after them;
+    // only a pair without its delimiters stops it, since nothing after that can be located
+    fn build(msg: &FeslMessage) -> FeslMessageIndex {
+        let mut entries: Vec<(usize, usize, usize, usize)> = Vec::new();
+        let mut iter = msg.raw_iter();
+        let mut complete = true;
+        while let Some(item) = iter.next_range() {
+            match item {
+                Ok((key, value)) => entries.push((key.start + 12, key.end + 12, value.start + 12, value.end + 12)),
+                Err(_) => {
+                    complete = false;
+                    break;
+                }
+            }
+        }
+        // stable, so the first occurrence of a repeated key stays first
+        entries.sort_by(|a, b| msg.data[a.0..a.1].cmp(&msg.data[b.0..b.1]));
+        FeslMessageIndex {
+            entries,
+            complete
+        }
+    }
+
+    // every occurrence of `key`, in the order they appear in the message
+    pub fn lookup(&self, data: &[u8], key: &str) -> &[(usize, usize, usize, usize)] {
+        let start = self.entries.partition_point(|x| &data[x.0..x.1] < key.as_bytes());
+        let end = self.entries.partition_point(|x| &data[x.0..x.1] <= key.as_bytes());
+        &self.entries[start..end]
+    }
+}
+
+impl FeslMessage {
+    pub(super) fn index(&self) -> &FeslMessageIndex {
+        self.index.get_or_init(|| FeslMessageIndex::build(self))
+    }
+
+    // every occurrence of `key`; a key that can't be found because the body is malformed
+    // before it is the parse error rather than a missing key
+    pub(super) fn lookup(&self, key: &str) -> FeslMessageResult<&[(usize, usize, usize, usize)]> {
+        let index = self.index();
+        let found = index.lookup(&self.data, key);
+        if found.is_empty() && !index.complete {
+            if let Some(Err(err)) = self.raw_iter().find(Result::is_err) {
+                return Err(err);
+            }
+        }
+        Ok(found)
+    }
+
+    // the value as it appears on the wire, still quoted and %xx encoded
+    pub fn get_raw(&self, key: &str) -> FeslMessageResult<Option<&str>> {
+        let x = match self.lookup(key)?.first() {
+            Some(x) => *x,
+            None => return Ok(None)
+        };
+        str::from_utf8(&self.data[x.2..x.3]).map(Some).map_err(|err| Error::from(err).at(x.2).with_key(key))
+    }
+
+    // the unescaped value, borrowed unless it had to be decoded
+    pub fn get(&self, key: &str) -> FeslMessageResult<Option<Cow<'_, str>>> {
+        match self.get_raw(key)? {
+            Some(value) => unescape(value).map(Some).map_err(|x| x.with_key(key)),
+            None => Ok(None)
+        }
+    }
+
+    pub fn require(&self, key: &str) -> FeslMessageResult<Cow<'_, str>> {
+        self.get(key)?.ok_or_else(|| Error::new(ErrorKind::MissingKey(key.to_string())))
+    }
+
+    fn get_parsed<T: FromStr>(&self, key: &str) -> FeslMessageResult<Option<T>> {
+        match self.get(key)? {
+            Some(value) => value.parse().map(Some).map_err(|_| Error::new(ErrorKind::InvalidValue(value.into_owned())).with_key(key)),
+            None => Ok(None)
+        }
+    }
+
+    fn require_parsed<T: FromStr>(&self, key: &str) -> FeslMessageResult<T> {
+        self.get_parsed(key)?.ok_or_else(|| Error::new(ErrorKind::MissingKey(key.to_string())))
+    }
+
+    pub fn get_u32(&self, key: &str) -> FeslMessageResult<Option<u32>> {
+        self.get_parsed(key)
+    }
+
+    pub fn get_i64(&self, key: &str) -> FeslMessageResult<Option<i64>> {
+        self.get_parsed(key)
+    }
+
+    pub fn get_bool(&self, key: &str) -> FeslMessageResult<Option<bool>> {
+        let value = match self.get(key)? {
+            Some(value) => value,
+            None => return Ok(None)
+        };
+        match &value[..] {
+            "1" | "true" => Ok(Some(true)),
+            "0" | "false" => Ok(Some(false)),
+            _ => Err(Error::new(ErrorKind::InvalidValue(value.into_owned())).with_key(key))
+        }
+    }
+
+    pub fn get_list_len(&self, key: &str) -> FeslMessageResult<Option<usize>> {
+        self.get_parsed(key)
+    }
+
+    pub fn require_u32(&self, key: &str) -> FeslMessageResult<u32> {
+        self.require_parsed(key)
+    }
+
+    pub fn require_i64(&self, key: &str) -> FeslMessageResult<i64> {
+        self.require_parsed(key)
+    }
+
+    pub fn require_bool(&self, key: &str) -> FeslMessageResult<bool> {
+        self.get_bool(key)?.ok_or_else(|| Error::new(ErrorKind::MissingKey(key.to_string())))
+    }
+
+    pub fn require_list_len(&self, key: &str) -> FeslMessageResult<usize> {
+        self.require_parsed(key)
+    }
+}
@@ -0,0 +1,106 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use error::ErrorKind;
+use super::{FeslCommand, FeslErrorCode, FeslErrorResponse, FeslMessage, FeslMessageBuilder, FeslMessageResult, FeslTransaction, FeslValue};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeslReply {
+    None,
+    // a map of the reply's keys; any TXN entry is replaced by the request's
+    Value(FeslValue),
+    Error(FeslErrorResponse)
+}
+
+impl FeslReply {
+    pub fn from_transaction<T: FeslTransaction>(value: &T) -> FeslReply {
+        FeslReply::Value(value.to_value())
+    }
+}
+
+impl From<FeslValue> for FeslReply {
+    fn from(value: FeslValue) -> FeslReply {
+        FeslReply::Value(value)
+    }
+}
+
+impl From<FeslErrorResponse> for FeslReply {
+    fn from(error: FeslErrorResponse) -> FeslReply {
+        FeslReply::Error(error)
+    }
+}
+
+type FeslHandler<C> = Box<dyn Fn(&mut C, &FeslMessage) -> FeslMessageResult<FeslReply> + Send + Sync>;
+
+// dispatches whole messages, so fragments have to go through FeslFragmentAssembler first
+pub struct FeslRouter<C> {
+    handlers: BTreeMap<FeslCommand, BTreeMap<String, FeslHandler<C>>>,
+    fallback: FeslHandler<C>
+}
+
+impl <C> FeslRouter<C> {
+    pub fn new() -> FeslRouter<C> {
+        FeslRouter {
+            handlers: BTreeMap::new(),
+            fallback: Box::new(|_, _| Ok(FeslErrorResponse::new(FeslErrorCode::SystemError).into()))
+        }
+    }
+
+    pub fn on_command<F>(&mut self, cmd: FeslCommand, txn: &str, handler: F) -> &mut FeslRouter<C>
+        where F: Fn(&mut C, &FeslMessage) -> FeslMessageResult<FeslReply> + Send + Sync + 'static {
+        self.handlers.entry(cmd).or_default().insert(txn.to_string(), Box::new(handler));
+        self
+    }
+
+    // requests that fail to parse are answered with a FieldInvalid error naming the offending key
+    pub fn on_transaction<T, F>(&mut self, handler: F) -> &mut FeslRouter<C>
+        where T: FeslTransaction, F: Fn(&mut C, T) -> FeslMessageResult<FeslReply> + Send + Sync + 'static {
+        self.on_command(T::CMD, T::TXN, move |ctx, msg| match T::from_message(msg) {
+            Ok(request) => handler(ctx, request),
+            Err(error) => {
+                let reply = FeslErrorResponse::new(FeslErrorCode::FieldInvalid);
+                let key = match *error.kind() {
+                    ErrorKind::MissingKey(ref key) => Some(&key[..]),
+                    _ => error.key()
+                };
+                Ok(match key {
+                    Some(key) => reply.with_field(key, 0, None),
+                    None => reply
+                }.into())
+            }
+        })
+    }
+
+    pub fn set_fallback<F>(&mut self, fallback: F) -> &mut FeslRouter<C>
+        where F: Fn(&mut C, &FeslMessage) -> FeslMessageResult<FeslReply> + Send + Sync + 'static {
+        self.fallback = Box::new(fallback);
+        self
+    }
+
+    // replies come back as builders so they can be built, written or split with build_fragments
+    pub fn dispatch<'a>(&self, ctx: &mut C, msg: &'a FeslMessage) -> FeslMessageResult<Option<FeslMessageBuilder<'a>>> {
+        let txn = msg.get("TXN")?;
+        let handler = txn.as_ref()
+            .and_then(|txn| self.handlers.get(&msg.get_command())?.get(&txn[..]))
+            .unwrap_or(&self.fallback);
+        let fesl_type = msg.get_message_type().reply();
+        let txn = txn.unwrap_or(Cow::Borrowed(""));
+        Ok(match handler(ctx, msg)? {
+            FeslReply::None => None,
+            FeslReply::Error(error) => Some(error.to_builder(msg.get_command(), fesl_type, txn, msg.get_id())),
+            FeslReply::Value(value) => {
+                let mut builder = FeslMessageBuilder::with_command(msg.get_command(), fesl_type, msg.get_id());
+                builder.push("TXN", txn);
+                builder.extend(value.flatten().into_iter().filter(|x| x.0 != "TXN"));
+                Some(builder)
+            }
+        })
+    }
+}
+
+impl <C> Default for FeslRouter<C> {
+    fn default() -> FeslRouter<C> {
+        FeslRouter::new()
+    }
+}
@@ -0,0 +1,337 @@
+use core::fmt;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::ser::{self, Impossible, Serialize};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use error::{Error, ErrorKind};
+use super::{FeslMessage, FeslMessageBuilder, FeslValue};
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Custom(msg.to_string()))
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Custom(msg.to_string()))
+    }
+}
+
+type FeslSerdeResult<T> = Result<T, Error>;
+
+pub fn to_builder<T: Serialize + ?Sized>(value: &T, builder: &mut FeslMessageBuilder) -> FeslSerdeResult<()> {
+    let value = match value.serialize(FeslValueSerializer)? {
+        Some(value @ FeslValue::Map(_)) => value,
+        _ => return Err(ErrorKind::ExpectedMap.into())
+    };
+    for (key, value) in value.flatten() {
+        builder.try_push(key, value)?;
+    }
+    Ok(())
+}
+
+pub fn from_message<T: DeserializeOwned>(msg: &FeslMessage) -> FeslSerdeResult<T> {
+    T::deserialize(FeslValue::from_message(msg)?)
+}
+
+// `None` stands for values FESL has no representation for, which are left out of the body
+struct FeslValueSerializer;
+
+impl ser::Serializer for FeslValueSerializer {
+    type Ok = Option<FeslValue>;
+    type Error = Error;
+    type SerializeSeq = FeslSeqSerializer;
+    type SerializeTuple = FeslSeqSerializer;
+    type SerializeTupleStruct = FeslSeqSerializer;
+    type SerializeTupleVariant = Impossible<Option<FeslValue>, Error>;
+    type SerializeMap = FeslMapSerializer;
+    type SerializeStruct = FeslMapSerializer;
+    type SerializeStructVariant = Impossible<Option<FeslValue>, Error>;
+
+    fn serialize_bool(self, v: bool) -> FeslSerdeResult<Self::Ok> {
+        self.serialize_str(if v { "true" } else { "false" })
+    }
+
+    fn serialize_i8(self, v: i8) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_i16(self, v: i16) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_i32(self, v: i32) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_i64(self, v: i64) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_u8(self, v: u8) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_u16(self, v: u16) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_u32(self, v: u32) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_u64(self, v: u64) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_f32(self, v: f32) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_f64(self, v: f64) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+    fn serialize_char(self, v: char) -> FeslSerdeResult<Self::Ok> { self.serialize_str(&v.to_string()) }
+
+    fn serialize_str(self, v: &str) -> FeslSerdeResult<Self::Ok> {
+        Ok(Some(FeslValue::String(v.to_string())))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> FeslSerdeResult<Self::Ok> {
+        Err(ErrorKind::UnsupportedType("bytes").into())
+    }
+
+    fn serialize_none(self) -> FeslSerdeResult<Self::Ok> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> FeslSerdeResult<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> FeslSerdeResult<Self::Ok> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> FeslSerdeResult<Self::Ok> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> FeslSerdeResult<Self::Ok> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> FeslSerdeResult<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> FeslSerdeResult<Self::Ok> {
+        Err(ErrorKind::UnsupportedType("newtype variant").into())
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> FeslSerdeResult<Self::SerializeSeq> {
+        Ok(FeslSeqSerializer {
+            buf: Vec::with_capacity(len.unwrap_or(0))
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> FeslSerdeResult<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> FeslSerdeResult<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> FeslSerdeResult<Self::SerializeTupleVariant> {
+        Err(ErrorKind::UnsupportedType("tuple variant").into())
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> FeslSerdeResult<Self::SerializeMap> {
+        Ok(FeslMapSerializer {
+            buf: Vec::with_capacity(len.unwrap_or(0)),
+            key: None
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> FeslSerdeResult<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> FeslSerdeResult<Self::SerializeStructVariant> {
+        Err(ErrorKind::UnsupportedType("struct variant").into())
+    }
+}
+
+struct FeslSeqSerializer {
+    buf: Vec<FeslValue>
+}
+
+impl FeslSeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> FeslSerdeResult<()> {
+        let value = value.serialize(FeslValueSerializer)?;
+        self.buf.push(value.unwrap_or_else(|| FeslValue::String(String::new())));
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for FeslSeqSerializer {
+    type Ok = Option<FeslValue>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> FeslSerdeResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> FeslSerdeResult<Self::Ok> {
+        Ok(Some(FeslValue::List(self.buf)))
+    }
+}
+
+impl ser::SerializeTuple for FeslSeqSerializer {
+    type Ok = Option<FeslValue>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> FeslSerdeResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> FeslSerdeResult<Self::Ok> {
+        Ok(Some(FeslValue::List(self.buf)))
+    }
+}
+
+impl ser::SerializeTupleStruct for FeslSeqSerializer {
+    type Ok = Option<FeslValue>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> FeslSerdeResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> FeslSerdeResult<Self::Ok> {
+        Ok(Some(FeslValue::List(self.buf)))
+    }
+}
+
+struct FeslMapSerializer {
+    buf: Vec<(String, FeslValue)>,
+    key: Option<String>
+}
+
+impl FeslMapSerializer {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> FeslSerdeResult<()> {
+        if let Some(value) = value.serialize(FeslValueSerializer)? {
+            self.buf.push((key, value));
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for FeslMapSerializer {
+    type Ok = Option<FeslValue>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> FeslSerdeResult<()> {
+        match key.serialize(FeslValueSerializer)? {
+            Some(FeslValue::String(key)) => {
+                self.key = Some(key);
+                Ok(())
+            },
+            _ => Err(ErrorKind::UnsupportedType("non-string map key").into())
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> FeslSerdeResult<()> {
+        let key = self.key.take().expect("serialize_value called before serialize_key");
+        self.insert(key, value)
+    }
+
+    fn end(self) -> FeslSerdeResult<Self::Ok> {
+        Ok(Some(FeslValue::Map(self.buf)))
+    }
+}
+
+impl ser::SerializeStruct for FeslMapSerializer {
+    type Ok = Option<FeslValue>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> FeslSerdeResult<()> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> FeslSerdeResult<Self::Ok> {
+        Ok(Some(FeslValue::Map(self.buf)))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> FeslSerdeResult<V::Value> {
+                match self {
+                    FeslValue::String(value) => match value.parse() {
+                        Ok(v) => visitor.$visit(v),
+                        Err(_) => Err(ErrorKind::InvalidValue(value).into())
+                    },
+                    value => value.deserialize_any(visitor)
+                }
+            }
+        )*
+    }
+}
+
+impl <'de> de::Deserializer<'de> for FeslValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> FeslSerdeResult<V::Value> {
+        match self {
+            FeslValue::String(value) => visitor.visit_string(value),
+            FeslValue::List(values) => {
+                let mut seq = SeqDeserializer::new(values.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            },
+            FeslValue::Map(entries) => {
+                let mut map = MapDeserializer::new(entries.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> FeslSerdeResult<V::Value> {
+        match self {
+            FeslValue::String(value) => match &value[..] {
+                "1" | "true" => visitor.visit_bool(true),
+                "0" | "false" => visitor.visit_bool(false),
+                _ => Err(ErrorKind::InvalidValue(value).into())
+            },
+            value => value.deserialize_any(visitor)
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> FeslSerdeResult<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> FeslSerdeResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> FeslSerdeResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> FeslSerdeResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> FeslSerdeResult<V::Value> {
+        match self {
+            FeslValue::String(value) => visitor.visit_enum(value.into_deserializer()),
+            _ => Err(ErrorKind::UnsupportedType("non-unit variant").into())
+        }
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl <'de> IntoDeserializer<'de, Error> for FeslValue {
+    type Deserializer = FeslValue;
+
+    fn into_deserializer(self) -> FeslValue {
+        self
+    }
+}
@@ -0,0 +1,62 @@
+use core::str;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use error::Error;
+use super::{FeslMessageResult, FeslRawIterator};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FeslTextMode {
+    // invalid pairs are reported as errors, but iteration continues with the next pair
+    #[default]
+    Strict,
+    Lossy,
+    Latin1
+}
+
+impl FeslTextMode {
+    pub fn decode<'a>(&self, src: &'a [u8]) -> Result<Cow<'a, str>, str::Utf8Error> {
+        match *self {
+            FeslTextMode::Strict => str::from_utf8(src).map(Cow::Borrowed),
+            FeslTextMode::Lossy => Ok(String::from_utf8_lossy(src)),
+            FeslTextMode::Latin1 if src.is_ascii() => Ok(Cow::Borrowed(str::from_utf8(src).unwrap())),
+            FeslTextMode::Latin1 => Ok(Cow::Owned(src.iter().map(|&x| x as char).collect()))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FeslDecodedIterator<'a> {
+    raw: FeslRawIterator<'a>,
+    mode: FeslTextMode
+}
+
+impl <'a> FeslRawIterator<'a> {
+    pub fn decoded(self, mode: FeslTextMode) -> FeslDecodedIterator<'a> {
+        FeslDecodedIterator {
+            raw: self,
+            mode
+        }
+    }
+}
+
+impl <'a> FeslDecodedIterator<'a> {
+    fn decode(&self, start: usize, src: &'a [u8]) -> FeslMessageResult<Cow<'a, str>> {
+        self.mode.decode(src).map_err(|x| Error::from(x).at(self.raw.offset + start + x.valid_up_to()))
+    }
+}
+
+impl <'a> Iterator for FeslDecodedIterator<'a> {
+    type Item = FeslMessageResult<(Cow<'a, str>, Cow<'a, str>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = match self.raw.next_range()? {
+            Ok(x) => x,
+            Err(v) => return Some(Err(v))
+        };
+        let src = self.raw.src;
+        Some(self.decode(key.start, &src[key.clone()]).and_then(|key| {
+            let value = self.decode(value.start, &src[value]).map_err(|x| x.with_key(&key))?;
+            Ok((key, value))
+        }))
+    }
+}
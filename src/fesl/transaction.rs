@@ -0,0 +1,117 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use error::{Error, ErrorKind};
+use super::{FeslCommand, FeslMessage, FeslMessageBuilder, FeslMessageResult, FeslMessageType, FeslValue};
+
+pub trait FeslField: Sized {
+    fn to_value(&self) -> Option<FeslValue>;
+
+    fn from_value(key: &str, value: Option<&FeslValue>) -> FeslMessageResult<Self>;
+}
+
+pub trait FeslTransaction: Sized {
+    const CMD: FeslCommand;
+    const TXN: &'static str;
+    const TYPE: FeslMessageType;
+
+    fn to_value(&self) -> FeslValue;
+
+    fn from_value(value: &FeslValue) -> FeslMessageResult<Self>;
+
+    fn to_message(&self, id: u32) -> FeslMessage {
+        let mut builder = FeslMessageBuilder::with_command(Self::CMD, Self::TYPE, id);
+        builder.extend(self.to_value().flatten());
+        builder.build()
+    }
+
+    fn from_message(msg: &FeslMessage) -> FeslMessageResult<Self> {
+        let cmd = msg.get_command();
+        if cmd != Self::CMD {
+            return Err(ErrorKind::UnexpectedCommand(cmd.to_string()).into());
+        }
+        let value = FeslValue::from_message(msg)?;
+        match value.get("TXN").and_then(FeslValue::as_str) {
+            Some(txn) if txn == Self::TXN => (),
+            Some(txn) => return Err(ErrorKind::UnexpectedTransaction(txn.to_string()).into()),
+            None => return Err(ErrorKind::MissingKey("TXN".to_string()).into())
+        }
+        Self::from_value(&value)
+    }
+}
+
+impl FeslField for String {
+    fn to_value(&self) -> Option<FeslValue> {
+        Some(FeslValue::String(self.clone()))
+    }
+
+    fn from_value(key: &str, value: Option<&FeslValue>) -> FeslMessageResult<Self> {
+        match value {
+            Some(FeslValue::String(value)) => Ok(value.clone()),
+            Some(_) => Err(Error::new(ErrorKind::ExpectedString).with_key(key)),
+            None => Err(ErrorKind::MissingKey(key.to_string()).into())
+        }
+    }
+}
+
+impl FeslField for bool {
+    fn to_value(&self) -> Option<FeslValue> {
+        Some(FeslValue::String(if *self { "true" } else { "false" }.to_string()))
+    }
+
+    fn from_value(key: &str, value: Option<&FeslValue>) -> FeslMessageResult<Self> {
+        let value = String::from_value(key, value)?;
+        match value.as_str() {
+            "1" | "true" => Ok(true),
+            "0" | "false" => Ok(false),
+            _ => Err(Error::new(ErrorKind::InvalidValue(value)).with_key(key))
+        }
+    }
+}
+
+macro_rules! impl_parsed_field {
+    ($($ty:ty),*) => {
+        $(
+            impl FeslField for $ty {
+                fn to_value(&self) -> Option<FeslValue> {
+                    Some(FeslValue::String(self.to_string()))
+                }
+
+                fn from_value(key: &str, value: Option<&FeslValue>) -> FeslMessageResult<Self> {
+                    let value = String::from_value(key, value)?;
+                    value.parse().map_err(|_| Error::new(ErrorKind::InvalidValue(value)).with_key(key))
+                }
+            }
+        )*
+    }
+}
+
+impl_parsed_field!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+impl <T: FeslField> FeslField for Option<T> {
+    fn to_value(&self) -> Option<FeslValue> {
+        self.as_ref().and_then(T::to_value)
+    }
+
+    fn from_value(key: &str, value: Option<&FeslValue>) -> FeslMessageResult<Self> {
+        match value {
+            Some(_) => Ok(Some(T::from_value(key, value)?)),
+            None => Ok(None)
+        }
+    }
+}
+
+impl <T: FeslField> FeslField for Vec<T> {
+    fn to_value(&self) -> Option<FeslValue> {
+        Some(FeslValue::List(self.iter().map(|x| x.to_value().unwrap_or_else(|| FeslValue::String(String::new()))).collect()))
+    }
+
+    fn from_value(key: &str, value: Option<&FeslValue>) -> FeslMessageResult<Self> {
+        match value {
+            Some(FeslValue::List(values)) => values.iter().enumerate().map(|(i, x)| {
+                T::from_value(&format!("{}.{}", key, i), Some(x))
+            }).collect(),
+            Some(_) => Err(Error::new(ErrorKind::ExpectedList).with_key(key)),
+            None => Err(ErrorKind::MissingKey(key.to_string()).into())
+        }
+    }
+}
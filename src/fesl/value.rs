@@ -0,0 +1,164 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use error::ErrorKind;
+use super::{FeslMessage, FeslMessageBuilder, FeslMessageResult};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeslValue {
+    String(String),
+    List(Vec<FeslValue>),
+    Map(Vec<(String, FeslValue)>)
+}
+
+impl FeslValue {
+    pub fn from_message(msg: &FeslMessage) -> FeslMessageResult<FeslValue> {
+        let mut root = FeslValueNode::Map(Vec::new(), BTreeMap::new());
+        for item in msg.into_iter().unescaped() {
+            let (key, value) = item?;
+            let path: Vec<&str> = key.split('.').collect();
+            root.insert(key, &path, &value)?;
+        }
+        root.finish("")
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            FeslValue::String(ref value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[FeslValue]> {
+        match *self {
+            FeslValue::List(ref values) => Some(values),
+            _ => None
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(String, FeslValue)]> {
+        match *self {
+            FeslValue::Map(ref entries) => Some(entries),
+            _ => None
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&FeslValue> {
+        self.as_map()?.iter().find(|x| x.0 == key).map(|x| &x.1)
+    }
+
+    pub fn flatten(&self) -> Vec<(String, String)> {
+        let mut buf = Vec::new();
+        self.flatten_into(String::new(), &mut buf);
+        buf
+    }
+
+    fn flatten_into(&self, prefix: String, buf: &mut Vec<(String, String)>) {
+        let join = |key: &str| if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+        match *self {
+            FeslValue::String(ref value) => buf.push((prefix.clone(), value.clone())),
+            FeslValue::List(ref values) => {
+                buf.push((join("[]"), values.len().to_string()));
+                for (i, value) in values.iter().enumerate() {
+                    value.flatten_into(join(&i.to_string()), buf);
+                }
+            },
+            FeslValue::Map(ref entries) => {
+                for (key, value) in entries {
+                    value.flatten_into(join(key), buf);
+                }
+            }
+        }
+    }
+}
+
+// the tree while a message is being read; each map keeps a key to position lookup next to
+// its entries so untrusted messages with many keys are read in O(n log n)
+enum FeslValueNode {
+    String(String),
+    Map(Vec<(String, FeslValueNode)>, BTreeMap<String, usize>)
+}
+
+impl FeslValueNode {
+    // lists are collected as maps holding a "[]" entry until the whole message is read
+    fn insert(&mut self, key: &str, path: &[&str], value: &str) -> FeslMessageResult<()> {
+        let (entries, positions) = match *self {
+            FeslValueNode::Map(ref mut entries, ref mut positions) => (entries, positions),
+            _ => return Err(ErrorKind::ConflictingKey(key.to_string()).into())
+        };
+        match positions.get(path[0]) {
+            Some(_) if path.len() == 1 => Err(ErrorKind::ConflictingKey(key.to_string()).into()),
+            Some(&pos) => entries[pos].1.insert(key, &path[1..], value),
+            None => {
+                let child = if path.len() == 1 {
+                    FeslValueNode::String(value.to_string())
+                } else {
+                    let mut child = FeslValueNode::Map(Vec::new(), BTreeMap::new());
+                    child.insert(key, &path[1..], value)?;
+                    child
+                };
+                positions.insert(path[0].to_string(), entries.len());
+                entries.push((path[0].to_string(), child));
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self, prefix: &str) -> FeslMessageResult<FeslValue> {
+        let (entries, positions) = match self {
+            FeslValueNode::Map(entries, positions) => (entries, positions),
+            FeslValueNode::String(value) => return Ok(FeslValue::String(value))
+        };
+        let join = |key: &str| if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+        let len = match positions.get("[]").map(|&pos| &entries[pos].1) {
+            Some(FeslValueNode::String(len)) => match len.parse::<usize>() {
+                Ok(len) => Some(len),
+                _ => return Err(ErrorKind::InvalidListLength(join("[]")).into())
+            },
+            Some(_) => return Err(ErrorKind::ConflictingKey(join("[]")).into()),
+            None => None
+        };
+        let len = match len {
+            Some(len) => len,
+            None => {
+                let mut buf = Vec::with_capacity(entries.len());
+                for (key, value) in entries {
+                    let value = value.finish(&join(&key))?;
+                    buf.push((key, value));
+                }
+                return Ok(FeslValue::Map(buf));
+            }
+        };
+        if entries.len() - 1 != len {
+            return Err(ErrorKind::ListLengthMismatch(join("[]"), len, entries.len() - 1).into());
+        }
+        let mut buf: Vec<Option<FeslValue>> = vec![None; len];
+        for (key, value) in entries {
+            if key == "[]" {
+                continue;
+            }
+            let index = match key.parse::<usize>() {
+                Ok(index) if index < len && buf[index].is_none() => index,
+                _ => return Err(ErrorKind::InvalidListIndex(join(&key)).into())
+            };
+            buf[index] = Some(value.finish(&join(&key))?);
+        }
+        Ok(FeslValue::List(buf.into_iter().map(Option::unwrap).collect()))
+    }
+}
+
+impl <'a> Extend<&'a (String, String)> for FeslMessageBuilder<'a> {
+    fn extend<T: IntoIterator<Item = &'a (String, String)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.push(key, value);
+        }
+    }
+}
+
+impl <'a> Extend<(String, String)> for FeslMessageBuilder<'a> {
+    fn extend<T: IntoIterator<Item = (String, String)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.push(key, value);
+        }
+    }
+}
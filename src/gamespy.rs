@@ -1,22 +1,16 @@
-use std::str;
+use core::str;
+use core::result::Result;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::io;
+#[cfg(feature = "std")]
 use std::io::{Write, BufRead, BufReader};
+#[cfg(feature = "std")]
 use std::net::TcpStream;
-use std::result::Result;
+use error::{Error, ErrorKind};
 
-#[derive(Debug)]
-pub enum GameSpyPacketError {
-    ExpectedDelimiter,
-    ExpectedUtf8(str::Utf8Error)
-}
-
-impl From<str::Utf8Error> for GameSpyPacketError {
-    fn from(error: str::Utf8Error) -> Self {
-        GameSpyPacketError::ExpectedUtf8(error)
-    }
-}
-
-type GameSpyPacketResult<T> = Result<T, GameSpyPacketError>;
+type GameSpyPacketResult<T> = Result<T, Error>;
 
 #[derive(Debug)]
 pub struct GameSpyPacket {
@@ -35,25 +29,29 @@ impl GameSpyPacket {
     }
 }
 
+#[cfg(feature = "std")]
 pub struct GameSpyPacketConsumer<'a> {
     src: &'a TcpStream,
     reader: BufReader<&'a TcpStream>
 }
 
+#[cfg(feature = "std")]
 impl <'a> GameSpyPacketConsumer<'a> {
     pub fn new(src: &'a TcpStream) -> GameSpyPacketConsumer<'a> {
         GameSpyPacketConsumer {
             src,
-            reader: BufReader::new(&src)
+            reader: BufReader::new(src)
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl <'a> Write for GameSpyPacketConsumer<'a> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.src.write(&buf) }
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.src.write(buf) }
     fn flush(&mut self) -> io::Result<()> { self.src.flush() }
 }
 
+#[cfg(feature = "std")]
 impl <'a> Iterator for GameSpyPacketConsumer<'a> {
     type Item = GameSpyPacket;
 
@@ -63,7 +61,7 @@ impl <'a> Iterator for GameSpyPacketConsumer<'a> {
         let mut rank = 0;
         loop {
             let read = {
-                let mut buf = self.reader.fill_buf().unwrap();
+                let buf = self.reader.fill_buf().unwrap();
                 let mut i = 0;
                 for &item in buf {
                     i += 1;
@@ -93,13 +91,14 @@ impl <'a> Iterator for GameSpyPacketConsumer<'a> {
 
 #[derive(Debug)]
 pub struct GameSpyPacketIterator<'a> {
-    src: &'a [u8]
+    src: &'a [u8],
+    offset: usize
 }
 
 impl <'a> GameSpyPacketIterator<'a> {
     fn shift_slice(&mut self, token: u8) -> GameSpyPacketResult<&'a [u8]> {
-        if self.src[0] != b'\\' {
-            return Err(GameSpyPacketError::ExpectedDelimiter);
+        if self.src.first() != Some(&b'\\') {
+            return Err(Error::new(ErrorKind::ExpectedDelimiter).at(self.offset));
         }
         let x = match self.src[1..].iter().position(|&x| x == token) {
             Some(x) => x + 1,
@@ -107,15 +106,19 @@ impl <'a> GameSpyPacketIterator<'a> {
         };
         let val = &self.src[1..x];
         self.src = &self.src[x..];
+        self.offset += x;
         Ok(val)
     }
 
     fn shift_str(&mut self, token: u8) -> GameSpyPacketResult<&'a str> {
-        Ok(str::from_utf8(self.shift_slice(token)?)?)
+        let offset = self.offset + 1;
+        str::from_utf8(self.shift_slice(token)?).map_err(|x| Error::from(x).at(offset + x.valid_up_to()))
     }
 
     fn read(&mut self) -> GameSpyPacketResult<(&'a str, &'a str)> {
-        Ok((&self.shift_str(b'\\')?, &self.shift_str(b'\\')?))
+        let key = self.shift_str(b'\\')?;
+        let value = self.shift_str(b'\\').map_err(|x| x.with_key(key))?;
+        Ok((key, value))
     }
 
     fn end<T, E>(&mut self, val: E) -> Result<T, E> {
@@ -141,7 +144,8 @@ impl <'a> IntoIterator for &'a GameSpyPacket {
 
     fn into_iter(self) -> Self::IntoIter {
         GameSpyPacketIterator {
-            src: &self.data[..self.data.len() - 7]
+            src: &self.data[..self.data.len() - 7],
+            offset: 0
         }
     }
 }
@@ -149,7 +153,13 @@ impl <'a> IntoIterator for &'a GameSpyPacket {
 #[derive(Debug)]
 pub struct GameSpyPacketBuilder<'a> {
     len: usize,
-    buf: Vec<(&'a str)>
+    buf: Vec<&'a str>
+}
+
+impl <'a> Default for GameSpyPacketBuilder<'a> {
+    fn default() -> GameSpyPacketBuilder<'a> {
+        GameSpyPacketBuilder::new()
+    }
 }
 
 impl <'a> GameSpyPacketBuilder<'a> {
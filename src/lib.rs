@@ -1,17 +1,51 @@
 //#![deny(warnings, missing_docs, missing_debug_implementations)]
 //#![crate_name = "fesl_codec"]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-#[macro_use] extern crate enum_primitive_derive;
+#[cfg(feature = "std")]
+extern crate core;
+#[macro_use]
+extern crate alloc;
 
-extern crate num_traits;
+extern crate base64;
+extern crate memchr;
 
+extern crate fesl_codec_derive;
+#[cfg(feature = "serde")]
+#[macro_use] extern crate serde;
+#[cfg(feature = "tokio")]
+extern crate bytes;
+#[cfg(feature = "tokio")]
+extern crate tokio_util;
+
+// lets code generated by fesl_codec_derive refer to `::fesl_codec` from within this crate
+extern crate self as fesl_codec;
+
+pub mod error;
 pub mod fesl;
 pub mod gamespy;
 
+pub use error::{Error, ErrorKind};
+
+// re-exported for code generated by fesl_codec_derive, so it also builds without std
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::string::String;
+    pub use alloc::vec::Vec;
+    pub use core::option::Option;
+    pub use core::result::Result;
+}
+
 #[cfg(test)]
 mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
     use super::fesl::*;
+    use super::{Error, ErrorKind};
+
+    const HELLO: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xb8, 0x54, 0x58, 0x4e, 0x3d, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3d, 0x6d, 0x6f, 0x68, 0x61, 0x69, 0x72, 0x2d, 0x70, 0x63, 0x0a, 0x73, 0x6b, 0x75, 0x3d, 0x31, 0x38, 0x32, 0x39, 0x38, 0x33, 0x31, 0x0a, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x65, 0x3d, 0x65, 0x6e, 0x5f, 0x55, 0x53, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x50, 0x6c, 0x61, 0x74, 0x66, 0x6f, 0x72, 0x6d, 0x3d, 0x50, 0x43, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x31, 0x2e, 0x31, 0x0a, 0x53, 0x44, 0x4b, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x33, 0x2e, 0x35, 0x2e, 0x32, 0x2e, 0x30, 0x2e, 0x39, 0x0a, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x63, 0x6f, 0x6c, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x32, 0x2e, 0x30, 0x0a, 0x66, 0x72, 0x61, 0x67, 0x6d, 0x65, 0x6e, 0x74, 0x53, 0x69, 0x7a, 0x65, 0x3d, 0x38, 0x30, 0x39, 0x36, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x54, 0x79, 0x70, 0x65, 0x3d, 0x73, 0x65, 0x72, 0x76, 0x65, 0x72, 0x0a, 0x00];
 
+    #[cfg(feature = "std")]
     #[test]
     fn it_parses() {
         let mut src: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xb8, 0x54, 0x58, 0x4e, 0x3d, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3d, 0x6d, 0x6f, 0x68, 0x61, 0x69, 0x72, 0x2d, 0x70, 0x63, 0x0a, 0x73, 0x6b, 0x75, 0x3d, 0x31, 0x38, 0x32, 0x39, 0x38, 0x33, 0x31, 0x0a, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x65, 0x3d, 0x65, 0x6e, 0x5f, 0x55, 0x53, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x50, 0x6c, 0x61, 0x74, 0x66, 0x6f, 0x72, 0x6d, 0x3d, 0x50, 0x43, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x31, 0x2e, 0x31, 0x0a, 0x53, 0x44, 0x4b, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x33, 0x2e, 0x35, 0x2e, 0x32, 0x2e, 0x30, 0x2e, 0x39, 0x0a, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x63, 0x6f, 0x6c, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x32, 0x2e, 0x30, 0x0a, 0x66, 0x72, 0x61, 0x67, 0x6d, 0x65, 0x6e, 0x74, 0x53, 0x69, 0x7a, 0x65, 0x3d, 0x38, 0x30, 0x39, 0x36, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x54, 0x79, 0x70, 0x65, 0x3d, 0x73, 0x65, 0x72, 0x76, 0x65, 0x72, 0x0a, 0x00];
@@ -33,10 +67,11 @@ mod tests {
         assert_eq!(iter.next().unwrap().unwrap(), ("protocolVersion", "2.0"));
         assert_eq!(iter.next().unwrap().unwrap(), ("fragmentSize", "8096"));
         assert_eq!(iter.next().unwrap().unwrap(), ("clientType", "server"));
-        assert_eq!(iter.next().is_none(), true);
-        assert_eq!(iter.next().is_none(), true);
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn it_stops_iter_on_error() {
         let mut src: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xb8, 0x54, 0x58, 0x4e, 0x3d, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3d, 0x6d, 0x6f, 0x68, 0x61, 0x69, 0x72, 0x2d, 0x70, 0x63, 0x0a, 0x73, 0x6b, 0x75, 0x3d, 0x31, 0x38, 0x32, 0x39, 0x38, 0x33, 0x31, 0x0a, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x65, 0x3d, 0x65, 0x6e, 0x5f, 0x55, 0x53, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x50, 0x6c, 0x61, 0x74, 0x66, 0x6f, 0x72, 0x6d, 0x3d, 0x50, 0x43, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x31, 0x2e, 0x31, 0x0a, 0x53, 0x44, 0x4b, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x33, 0x2e, 0x35, 0x2e, 0x32, 0x2e, 0x30, 0x2e, 0x39, 0x0a, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x63, 0x6f, 0x6c, 0x56, 0x65, 0x72, 0x73, 0xff, 0x6f, 0x6e, 0x3d, 0x32, 0x2e, 0x30, 0x0a, 0x66, 0x72, 0x61, 0x67, 0x6d, 0x65, 0x6e, 0x74, 0x53, 0x69, 0x7a, 0x65, 0x3d, 0x38, 0x30, 0x39, 0x36, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x54, 0x79, 0x70, 0x65, 0x3d, 0x73, 0x65, 0x72, 0x76, 0x65, 0x72, 0x0a, 0x00];
@@ -55,11 +90,12 @@ mod tests {
         iter.next();
         iter.next();
         assert_eq!(iter.next().unwrap().unwrap(), ("SDKVersion", "3.5.2.0.9"));
-        assert_eq!(iter.next().unwrap().is_err(), true);
-        assert_eq!(iter.next().is_none(), true);
-        assert_eq!(iter.next().is_none(), true);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn it_errors_on_invalid_terminator() {
         let mut src: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xb8, 0x54, 0x58, 0x4e, 0x3d, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3d, 0x6d, 0x6f, 0x68, 0x61, 0x69, 0x72, 0x2d, 0x70, 0x63, 0x0a, 0x73, 0x6b, 0x75, 0x3d, 0x31, 0x38, 0x32, 0x39, 0x38, 0x33, 0x31, 0x0a, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x65, 0x3d, 0x65, 0x6e, 0x5f, 0x55, 0x53, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x50, 0x6c, 0x61, 0x74, 0x66, 0x6f, 0x72, 0x6d, 0x3d, 0x50, 0x43, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x31, 0x2e, 0x31, 0x0a, 0x53, 0x44, 0x4b, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x33, 0x2e, 0x35, 0x2e, 0x32, 0x2e, 0x30, 0x2e, 0x39, 0x0a, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x63, 0x6f, 0x6c, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x32, 0x2e, 0x30, 0x0a, 0x66, 0x72, 0x61, 0x67, 0x6d, 0x65, 0x6e, 0x74, 0x53, 0x69, 0x7a, 0x65, 0x3d, 0x38, 0x30, 0x39, 0x36, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x54, 0x79, 0x70, 0x65, 0x3d, 0x73, 0x65, 0x72, 0x76, 0x65, 0x72, 0x0a, 0x01];
@@ -81,11 +117,12 @@ mod tests {
         iter.next();
         iter.next();
         assert_eq!(iter.next().unwrap().unwrap(), ("clientType", "server"));
-        assert_eq!(iter.next().unwrap().is_err(), true);
-        assert_eq!(iter.next().is_none(), true);
-        assert_eq!(iter.next().is_none(), true);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn it_bytes_correctly() {
         let mut src: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xb8, 0x54, 0x58, 0x4e, 0x3d, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3d, 0x6d, 0x6f, 0x68, 0x61, 0x69, 0x72, 0x2d, 0x70, 0x63, 0x0a, 0x73, 0x6b, 0x75, 0x3d, 0x31, 0x38, 0x32, 0x39, 0x38, 0x33, 0x31, 0x0a, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x65, 0x3d, 0x65, 0x6e, 0x5f, 0x55, 0x53, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x50, 0x6c, 0x61, 0x74, 0x66, 0x6f, 0x72, 0x6d, 0x3d, 0x50, 0x43, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x31, 0x2e, 0x31, 0x0a, 0x53, 0x44, 0x4b, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x33, 0x2e, 0x35, 0x2e, 0x32, 0x2e, 0x30, 0x2e, 0x39, 0x0a, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x63, 0x6f, 0x6c, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x32, 0x2e, 0x30, 0x0a, 0x66, 0x72, 0x61, 0x67, 0x6d, 0x65, 0x6e, 0x74, 0x53, 0x69, 0x7a, 0x65, 0x3d, 0x38, 0x30, 0x39, 0x36, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x54, 0x79, 0x70, 0x65, 0x3d, 0x73, 0x65, 0x72, 0x76, 0x65, 0x72, 0x0a, 0x00];
@@ -109,8 +146,8 @@ mod tests {
         assert_eq!(iter.next().unwrap().unwrap(), ("protocolVersion", "2.0"));
         assert_eq!(iter.next().unwrap().unwrap(), ("fragmentSize", "8096"));
         assert_eq!(iter.next().unwrap().unwrap(), ("clientType", "server"));
-        assert_eq!(iter.next().is_none(), true);
-        assert_eq!(iter.next().is_none(), true);
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
     }
 
     #[test]
@@ -120,6 +157,7 @@ mod tests {
         FeslMessageBuilder::new(bad_cmd, FeslMessageType::SingleClient, 0);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn it_builds_correctly() {
         let mut src: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xb8, 0x54, 0x58, 0x4e, 0x3d, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3d, 0x6d, 0x6f, 0x68, 0x61, 0x69, 0x72, 0x2d, 0x70, 0x63, 0x0a, 0x73, 0x6b, 0x75, 0x3d, 0x31, 0x38, 0x32, 0x39, 0x38, 0x33, 0x31, 0x0a, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x65, 0x3d, 0x65, 0x6e, 0x5f, 0x55, 0x53, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x50, 0x6c, 0x61, 0x74, 0x66, 0x6f, 0x72, 0x6d, 0x3d, 0x50, 0x43, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x31, 0x2e, 0x31, 0x0a, 0x53, 0x44, 0x4b, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x33, 0x2e, 0x35, 0x2e, 0x32, 0x2e, 0x30, 0x2e, 0x39, 0x0a, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x63, 0x6f, 0x6c, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x32, 0x2e, 0x30, 0x0a, 0x66, 0x72, 0x61, 0x67, 0x6d, 0x65, 0x6e, 0x74, 0x53, 0x69, 0x7a, 0x65, 0x3d, 0x38, 0x30, 0x39, 0x36, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x54, 0x79, 0x70, 0x65, 0x3d, 0x73, 0x65, 0x72, 0x76, 0x65, 0x72, 0x0a, 0x00];
@@ -133,7 +171,7 @@ mod tests {
 
         for item in &msg {
             match item {
-                Ok((key, value)) => msg_builder.push(&key, &value),
+                Ok((key, value)) => msg_builder.push(key, value),
                 Err(_) => panic!("Error during iteration")
             }
         }
@@ -142,10 +180,1422 @@ mod tests {
         assert_eq!(msg.as_bytes(), msg_clone.as_bytes());
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn it_verifies_types() {
         let mut src: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xa0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xb8, 0x54, 0x58, 0x4e, 0x3d, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3d, 0x6d, 0x6f, 0x68, 0x61, 0x69, 0x72, 0x2d, 0x70, 0x63, 0x0a, 0x73, 0x6b, 0x75, 0x3d, 0x31, 0x38, 0x32, 0x39, 0x38, 0x33, 0x31, 0x0a, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x65, 0x3d, 0x65, 0x6e, 0x5f, 0x55, 0x53, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x50, 0x6c, 0x61, 0x74, 0x66, 0x6f, 0x72, 0x6d, 0x3d, 0x50, 0x43, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x31, 0x2e, 0x31, 0x0a, 0x53, 0x44, 0x4b, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x33, 0x2e, 0x35, 0x2e, 0x32, 0x2e, 0x30, 0x2e, 0x39, 0x0a, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x63, 0x6f, 0x6c, 0x56, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x3d, 0x32, 0x2e, 0x30, 0x0a, 0x66, 0x72, 0x61, 0x67, 0x6d, 0x65, 0x6e, 0x74, 0x53, 0x69, 0x7a, 0x65, 0x3d, 0x38, 0x30, 0x39, 0x36, 0x0a, 0x63, 0x6c, 0x69, 0x65, 0x6e, 0x74, 0x54, 0x79, 0x70, 0x65, 0x3d, 0x73, 0x65, 0x72, 0x76, 0x65, 0x72, 0x0a, 0x00];
         let msg = FeslMessage::from_read(&mut src).unwrap();
-        assert_eq!(msg.get_type().is_err(), true);
+        assert!(msg.get_type().is_err());
+    }
+
+    #[test]
+    fn it_decodes_byte_by_byte() {
+        let mut decoder = FeslDecoder::new();
+
+        for byte in &HELLO[..HELLO.len() - 1] {
+            decoder.push(&[*byte]);
+            assert!(decoder.decode().unwrap().is_none());
+        }
+        decoder.push(&HELLO[HELLO.len() - 1..]);
+
+        let msg = decoder.decode().unwrap().unwrap();
+        assert_eq!(msg.as_bytes(), HELLO);
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn it_decodes_multiple_messages_from_one_chunk() {
+        let mut src: Vec<u8> = Vec::new();
+        src.extend(HELLO);
+        src.extend(HELLO);
+        src.extend(&HELLO[..20]);
+
+        let mut decoder = FeslDecoder::new();
+        decoder.push(&src);
+
+        let msg = decoder.next().unwrap().unwrap();
+        assert_eq!(msg.as_bytes(), HELLO);
+        let msg = decoder.next().unwrap().unwrap();
+        assert_eq!(msg.get_cmd().unwrap(), "fsys");
+        assert_eq!(msg.into_iter().count(), 10);
+        assert!(decoder.next().is_none());
+        assert_eq!(decoder.buffered(), 20);
+
+        decoder.push(&HELLO[20..]);
+        assert_eq!(decoder.next().unwrap().unwrap().as_bytes(), HELLO);
+        assert!(decoder.next().is_none());
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn it_errors_on_invalid_decoder_length() {
+        let mut decoder = FeslDecoder::new();
+        decoder.push(&[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0b, 0x00]);
+
+        assert!(decoder.next().unwrap().is_err());
+        assert!(decoder.next().is_none());
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_rejects_bad_lengths_on_read() {
+        let mut src: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x04];
+        match FeslMessage::from_read(&mut src).map_err(Error::into_kind) {
+            Err(ErrorKind::MessageTooSmall(4)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let mut src: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff];
+        match FeslMessage::from_read(&mut src).map_err(Error::into_kind) {
+            Err(ErrorKind::MessageTooLarge(0xffffffff)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let mut src: &[u8] = &HELLO[..100];
+        match FeslMessage::from_read(&mut src).map_err(Error::into_kind) {
+            Err(ErrorKind::Io(_)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_applies_decode_policy() {
+        let policy = FeslDecodePolicy {
+            max_len: 100,
+            ..FeslDecodePolicy::default()
+        };
+        let mut src: &[u8] = HELLO;
+        match FeslMessage::from_read_with(&mut src, &policy).map_err(Error::into_kind) {
+            Err(ErrorKind::MessageTooLarge(184)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let policy = FeslDecodePolicy {
+            require_terminator: true,
+            reject_trailing: true,
+            ..FeslDecodePolicy::default()
+        };
+        let mut src: &[u8] = HELLO;
+        assert!(FeslMessage::from_read_with(&mut src, &policy).is_ok());
+
+        let mut unterminated = HELLO.to_vec();
+        unterminated[183] = 0x01;
+        match FeslMessage::from_read_with(&mut &unterminated[..], &policy).map_err(Error::into_kind) {
+            Err(ErrorKind::ExpectedTerminator) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let mut trailing = HELLO.to_vec();
+        trailing[170] = 0x00;
+        match FeslMessage::from_read_with(&mut &trailing[..], &policy).map_err(Error::into_kind) {
+            Err(ErrorKind::TrailingBytes(13)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let mut decoder = FeslDecoder::with_policy(policy);
+        decoder.push(&trailing);
+        assert!(decoder.next().unwrap().is_err());
+        assert!(decoder.next().is_none());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn it_decodes_with_tokio_codec() {
+        use bytes::BytesMut;
+        use tokio_util::codec::{Decoder, Encoder};
+
+        let mut codec = FeslCodec::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(&HELLO[..50]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert!(buf.capacity() >= HELLO.len());
+
+        buf.extend_from_slice(&HELLO[50..]);
+        buf.extend_from_slice(&HELLO[..5]);
+        let msg = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(msg.as_bytes(), HELLO);
+        assert_eq!(buf.len(), 5);
+        assert!(codec.decode_eof(&mut buf).is_err());
+
+        let mut out = BytesMut::new();
+        codec.encode(&msg, &mut out).unwrap();
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleServer, 1);
+        builder.push("TXN", "Hello");
+        codec.encode(builder, &mut out).unwrap();
+
+        assert_eq!(codec.decode(&mut out).unwrap().unwrap().as_bytes(), HELLO);
+        let msg = codec.decode(&mut out).unwrap().unwrap();
+        assert_eq!(msg.get_type().unwrap(), FeslMessageType::SingleServer);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn it_reassembles_fragments() {
+        let mut assembler = FeslFragmentAssembler::new();
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::MultiServer, 7);
+        builder.push("size", "40");
+        builder.push("decodedSize", "28");
+        builder.push_raw("data", "VFhOPUhlbGxvCmNsaWVudF");
+        assert!(assembler.push(builder.build()).unwrap().is_none());
+        assert_eq!(assembler.pending(), 1);
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::MultiServer, 7);
+        builder.push("size", "40");
+        builder.push("decodedSize", "28");
+        builder.push_raw("data", "R5cGU9c2VydmVyCg%3d%3d");
+        let msg = assembler.push(builder.build()).unwrap().unwrap();
+        assert_eq!(assembler.pending(), 0);
+
+        assert_eq!(msg.get_cmd().unwrap(), "fsys");
+        assert_eq!(msg.get_type().unwrap(), FeslMessageType::SingleServer);
+        assert_eq!(msg.get_id(), 7);
+        assert_eq!(msg.as_bytes().len(), 12 + 28 + 1);
+
+        let mut iter = msg.into_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), ("TXN", "Hello"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("clientType", "server"));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn it_passes_through_single_messages_and_rejects_bad_fragments() {
+        use core::convert::TryFrom;
+
+        let mut assembler = FeslFragmentAssembler::new();
+        let msg = assembler.push(FeslMessage::try_from(HELLO).unwrap()).unwrap().unwrap();
+        assert_eq!(msg.as_bytes(), HELLO);
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::MultiClient, 3);
+        builder.push("size", "4");
+        builder.push("decodedSize", "2");
+        builder.push("data", "VFhO");
+        match assembler.push(builder.build()).map_err(Error::into_kind) {
+            Err(ErrorKind::FragmentSizeMismatch(2, 3)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::MultiClient, 3);
+        builder.push("size", "4");
+        builder.push("data", "VFhO");
+        match assembler.push(builder.build()).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidFragment) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert_eq!(assembler.pending(), 0);
+    }
+
+    #[test]
+    fn it_bounds_fragment_buffers() {
+        let fragment = |id: u32, size: &str, decoded_size: &str| {
+            let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::MultiClient, id);
+            builder.push("size", size);
+            builder.push("decodedSize", decoded_size);
+            builder.push("data", "VFhO");
+            builder.build()
+        };
+
+        let mut assembler = FeslFragmentAssembler::new();
+        match assembler.push(fragment(1, "1000000000000", "9")).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidFragment) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        match assembler.push(fragment(1, "1000000000000", "750000000000")).map_err(Error::into_kind) {
+            Err(ErrorKind::MessageTooLarge(_)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert_eq!(assembler.pending(), 0);
+
+        let mut assembler = FeslFragmentAssembler::with_policy(FeslDecodePolicy::default(), FeslFragmentPolicy {
+            max_pending: 2,
+            max_buffered: 20
+        });
+        assert!(assembler.push(fragment(1, "8", "6")).unwrap().is_none());
+        assert!(assembler.push(fragment(2, "8", "6")).unwrap().is_none());
+        match assembler.push(fragment(3, "8", "6")).map_err(Error::into_kind) {
+            Err(ErrorKind::TooManyPending(2)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert!(assembler.push(fragment(2, "8", "6")).unwrap().is_some());
+        match assembler.push(fragment(3, "16", "12")).map_err(Error::into_kind) {
+            Err(ErrorKind::MessageTooLarge(24)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert!(assembler.push(fragment(3, "12", "9")).unwrap().is_none());
+        assert_eq!(assembler.pending(), 2);
+    }
+
+    #[test]
+    fn it_builds_fragments_within_size() {
+        use core::convert::TryFrom;
+
+        let msg = FeslMessage::try_from(HELLO).unwrap();
+        let builder = || {
+            let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 1);
+            for item in &msg {
+                let (key, value) = item.unwrap();
+                builder.push_raw(key, value);
+            }
+            builder
+        };
+
+        let fragments = builder().build_fragments(8096).unwrap();
+        assert_eq!(fragments.len(), 1);
+        assert_eq!(fragments[0].as_bytes(), HELLO);
+
+        let fragments = builder().build_fragments(80).unwrap();
+        assert_eq!(fragments.len(), 7);
+
+        let mut assembler = FeslFragmentAssembler::new();
+        let mut result = None;
+        for fragment in fragments {
+            assert!(fragment.as_bytes().len() <= 80);
+            assert_eq!(fragment.get_type().unwrap(), FeslMessageType::MultiClient);
+            assert_eq!(fragment.get_id(), 1);
+            assert!(result.is_none());
+            result = assembler.push(fragment).unwrap();
+        }
+        assert_eq!(result.unwrap().as_bytes(), HELLO);
+    }
+
+    #[test]
+    fn it_escapes_fragment_padding() {
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleServer, 1);
+        builder.push("TXN", "Hello");
+        builder.push("theaterIp", "192.168.100.1");
+        builder.push("clientString", "mohair-pc");
+        builder.push("locale", "en_US");
+        let fragments = builder.build_fragments(60).unwrap();
+        assert_eq!(fragments.len(), 7);
+        let data: Vec<_> = fragments.iter().map(|x| x.get_raw("data").unwrap().unwrap().to_string()).collect();
+        assert!(fragments.iter().all(|x| x.as_bytes().len() <= 60));
+        assert!(data.last().unwrap().ends_with("%3d%3d"));
+        assert!(!data.concat().contains('='));
+
+        let mut assembler = FeslFragmentAssembler::new();
+        let mut result = None;
+        for fragment in fragments {
+            assert_eq!(fragment.get_message_type(), FeslMessageType::MultiServer);
+            result = assembler.push(fragment).unwrap();
+        }
+        assert_eq!(result.unwrap().get("theaterIp").unwrap().unwrap(), "192.168.100.1");
+    }
+
+    #[test]
+    fn it_errors_on_tiny_fragment_size() {
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleServer, 1);
+        builder.push("TXN", "Hello");
+        match builder.build_fragments(20).map_err(Error::into_kind) {
+            Err(ErrorKind::FragmentSizeTooSmall(20)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::MultiClient, 1);
+        builder.push("TXN", "Hello");
+        match builder.build_fragments(8096).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidType(x)) if x == FeslMessageType::MultiClient.bits() => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[test]
+    fn it_parses_value_tree() {
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleServer, 4);
+        builder.push("TXN", "NuGetPersonas");
+        builder.push("personas.[]", "2");
+        builder.push("personas.0.name", "foo");
+        builder.push("personas.1.name", "bar");
+        builder.push("stats.[]", "0");
+        builder.push("owner.id", "1");
+        let msg = builder.build();
+
+        let value = FeslValue::from_message(&msg).unwrap();
+        assert_eq!(value.get("TXN").unwrap().as_str(), Some("NuGetPersonas"));
+        let personas = value.get("personas").unwrap().as_list().unwrap();
+        assert_eq!(personas.len(), 2);
+        assert_eq!(personas[1].get("name").unwrap().as_str(), Some("bar"));
+        assert_eq!(value.get("stats"), Some(&FeslValue::List(Vec::new())));
+        assert_eq!(value.get("owner").unwrap().get("id").unwrap().as_str(), Some("1"));
+
+        let pairs = value.flatten();
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleServer, 4);
+        builder.extend(&pairs);
+        assert_eq!(builder.build().as_bytes(), msg.as_bytes());
+    }
+
+    #[test]
+    fn it_errors_on_inconsistent_value_tree() {
+        let parse = |pairs: &[(&str, &str)]| {
+            let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleServer, 4);
+            for &(key, value) in pairs {
+                builder.push(key, value);
+            }
+            FeslValue::from_message(&builder.build())
+        };
+
+        match parse(&[("personas.[]", "2"), ("personas.0", "foo")]).map_err(Error::into_kind) {
+            Err(ErrorKind::ListLengthMismatch(ref key, 2, 1)) if key == "personas.[]" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        match parse(&[("personas.[]", "1"), ("personas.1", "foo")]).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidListIndex(ref key)) if key == "personas.1" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        match parse(&[("personas.[]", "x")]).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidListLength(ref key)) if key == "personas.[]" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        match parse(&[("owner", "1"), ("owner.id", "1")]).map_err(Error::into_kind) {
+            Err(ErrorKind::ConflictingKey(ref key)) if key == "owner.id" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    // quadratic inserts take minutes here, so a regression shows up as a hung test
+    #[test]
+    fn it_parses_wide_value_trees() {
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 1);
+        for i in 0..100000 {
+            builder.push(format!("a{}", i), "");
+        }
+        builder.push_u32("list.[]", 10000);
+        for i in 0..10000 {
+            builder.push_u32(format!("list.{}", i), i);
+        }
+        let value = FeslValue::from_message(&builder.build()).unwrap();
+        assert_eq!(value.as_map().unwrap().len(), 100001);
+        assert_eq!(value.get("a99999").and_then(FeslValue::as_str), Some(""));
+        assert_eq!(value.get("list").and_then(FeslValue::as_list).unwrap()[9999].as_str(), Some("9999"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[allow(non_snake_case)]
+    struct Persona {
+        name: String,
+        userId: u32
+    }
+
+    #[cfg(feature = "serde")]
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[allow(non_snake_case)]
+    struct NuGetPersonas {
+        TXN: String,
+        personas: Vec<Persona>,
+        owner: Option<u64>,
+        isPrimary: bool
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_with_serde() {
+        let value = NuGetPersonas {
+            TXN: "NuGetPersonas".to_string(),
+            personas: vec![
+                Persona { name: "foo".to_string(), userId: 1 },
+                Persona { name: "bar".to_string(), userId: 2 }
+            ],
+            owner: None,
+            isPrimary: true
+        };
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleServer, 4);
+        to_builder(&value, &mut builder).unwrap();
+        let msg = builder.build();
+
+        let mut iter = msg.into_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), ("TXN", "NuGetPersonas"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.[]", "2"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.0.name", "foo"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.0.userId", "1"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.1.name", "bar"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.1.userId", "2"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("isPrimary", "true"));
+        assert!(iter.next().is_none());
+
+        assert_eq!(from_message::<NuGetPersonas>(&msg).unwrap(), value);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_deserializes_with_serde() {
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 4);
+        builder.push("TXN", "NuGetPersonas");
+        builder.push("owner", "12");
+        builder.push("isPrimary", "0");
+        builder.push("personas.[]", "0");
+        let value: NuGetPersonas = from_message(&builder.build()).unwrap();
+        assert_eq!(value.owner, Some(12));
+        assert!(!value.isPrimary);
+        assert!(value.personas.is_empty());
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 4);
+        builder.push("TXN", "NuGetPersonas");
+        builder.push("isPrimary", "maybe");
+        builder.push("personas.[]", "0");
+        match from_message::<NuGetPersonas>(&builder.build()).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidValue(ref value)) if value == "maybe" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[derive(Debug, PartialEq, FeslTransaction)]
+    #[fesl(cmd = "acct", txn = "NuLogin")]
+    struct NuLogin {
+        #[fesl(rename = "nuid")]
+        email: String,
+        password: String,
+        #[fesl(rename = "returnEncryptedInfo")]
+        return_encrypted_info: bool,
+        #[fesl(rename = "macAddr")]
+        mac_addr: Option<String>
+    }
+
+    #[derive(Debug, PartialEq, FeslTransaction)]
+    #[fesl(cmd = "acct", txn = "NuGetPersonas", type = "SingleServer")]
+    struct NuGetPersonasResponse {
+        personas: Vec<String>
+    }
+
+    #[test]
+    fn it_derives_transactions() {
+        let login = NuLogin {
+            email: "user@example.com".to_string(),
+            password: "hunter2".to_string(),
+            return_encrypted_info: false,
+            mac_addr: None
+        };
+        let msg = login.to_message(3);
+        assert_eq!(msg.get_cmd().unwrap(), "acct");
+        assert_eq!(msg.get_type().unwrap(), FeslMessageType::SingleClient);
+        assert_eq!(msg.get_id(), 3);
+
+        let mut iter = msg.into_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), ("TXN", "NuLogin"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("nuid", "user@example.com"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("password", "hunter2"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("returnEncryptedInfo", "false"));
+        assert!(iter.next().is_none());
+        assert_eq!(NuLogin::from_message(&msg).unwrap(), login);
+
+        let response = NuGetPersonasResponse {
+            personas: vec!["foo".to_string(), "bar".to_string()]
+        };
+        let msg = response.to_message(3);
+        assert_eq!(msg.get_type().unwrap(), FeslMessageType::SingleServer);
+        assert_eq!(NuGetPersonasResponse::from_message(&msg).unwrap(), response);
+    }
+
+    #[derive(Debug, Clone, PartialEq, FeslField)]
+    struct PersonaDetails {
+        #[fesl(rename = "personaId")]
+        persona_id: u32,
+        name: String
+    }
+
+    #[derive(Debug, PartialEq, FeslTransaction)]
+    #[fesl(cmd = "acct", txn = "NuGetPersonaDetails", type = "SingleServer")]
+    struct NuGetPersonaDetailsResponse {
+        owner: PersonaDetails,
+        personas: Vec<PersonaDetails>
+    }
+
+    #[test]
+    fn it_derives_nested_fields() {
+        let persona = PersonaDetails { persona_id: 1, name: "foo".to_string() };
+        let response = NuGetPersonaDetailsResponse {
+            owner: persona.clone(),
+            personas: vec![persona, PersonaDetails { persona_id: 2, name: "bar".to_string() }]
+        };
+        let msg = response.to_message(4);
+
+        let mut iter = msg.into_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), ("TXN", "NuGetPersonaDetails"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("owner.personaId", "1"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("owner.name", "foo"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.[]", "2"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.0.personaId", "1"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.0.name", "foo"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.1.personaId", "2"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("personas.1.name", "bar"));
+        assert!(iter.next().is_none());
+        assert_eq!(NuGetPersonaDetailsResponse::from_message(&msg).unwrap(), response);
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleServer, 4);
+        builder.push("TXN", "NuGetPersonaDetails");
+        builder.push("owner.personaId", "1");
+        builder.push("owner.name", "foo");
+        builder.push("personas.[]", "1");
+        builder.push("personas.0.name", "bar");
+        match NuGetPersonaDetailsResponse::from_message(&builder.build()).map_err(Error::into_kind) {
+            Err(ErrorKind::MissingKey(ref key)) if key == "personas.0.personaId" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[test]
+    fn it_rejects_mismatched_transactions() {
+        let msg = NuGetPersonasResponse { personas: Vec::new() }.to_message(1);
+        match NuLogin::from_message(&msg).map_err(Error::into_kind) {
+            Err(ErrorKind::UnexpectedTransaction(ref txn)) if txn == "NuGetPersonas" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 1);
+        builder.push("TXN", "NuLogin");
+        builder.push("nuid", "user@example.com");
+        builder.push("returnEncryptedInfo", "1");
+        match NuLogin::from_message(&builder.build()).map_err(Error::into_kind) {
+            Err(ErrorKind::MissingKey(ref key)) if key == "password" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 1);
+        builder.push("TXN", "NuLogin");
+        match NuLogin::from_message(&builder.build()).map_err(Error::into_kind) {
+            Err(ErrorKind::UnexpectedCommand(ref cmd)) if cmd == "fsys" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[test]
+    fn it_escapes_values() {
+        use alloc::borrow::Cow;
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleServer, 2);
+        builder.push("localizedMessage", "The password is incorrect");
+        builder.push("name", "a=b\n\"c\" 100%");
+        builder.push("nick", "Jos\u{e9}");
+        builder.push("plain", "ok");
+        let msg = builder.build();
+
+        let mut iter = msg.into_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), ("localizedMessage", "\"The password is incorrect\""));
+        assert_eq!(iter.next().unwrap().unwrap(), ("name", "\"a%3db%0a%22c%22 100%25\""));
+        assert_eq!(iter.next().unwrap().unwrap(), ("nick", "\"Jos%c3%a9\""));
+        assert_eq!(iter.next().unwrap().unwrap(), ("plain", "ok"));
+
+        let mut iter = msg.into_iter().unescaped();
+        assert_eq!(iter.next().unwrap().unwrap(), ("localizedMessage", Cow::Borrowed("The password is incorrect")));
+        assert_eq!(iter.next().unwrap().unwrap().1, "a=b\n\"c\" 100%");
+        assert_eq!(iter.next().unwrap().unwrap().1, "Jos\u{e9}");
+        match iter.next().unwrap().unwrap().1 {
+            Cow::Borrowed("ok") => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn it_unescapes_leniently() {
+        assert_eq!(unescape("VFhO%3d%3D").unwrap(), "VFhO==");
+        assert_eq!(unescape("50%").unwrap(), "50%");
+        assert_eq!(unescape("%zz%4").unwrap(), "%zz%4");
+        assert_eq!(unescape("\"\"").unwrap(), "");
+        assert!(unescape("%ff").is_err());
+        assert_eq!(escape("plain"), "plain");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_reports_error_locations() {
+        let mut src = HELLO.to_vec();
+        src[139] = 0xff;
+        let msg = FeslMessage::from_read(&mut &src[..]).unwrap();
+        let err = msg.into_iter().find(|x| x.is_err()).unwrap().unwrap_err();
+        assert_eq!(err.offset(), Some(139));
+        assert_eq!(err.key(), None);
+
+        let mut src = HELLO.to_vec();
+        src[161] = 0xff;
+        let msg = FeslMessage::from_read(&mut &src[..]).unwrap();
+        let err = msg.into_iter().find(|x| x.is_err()).unwrap().unwrap_err();
+        assert_eq!(err.offset(), Some(161));
+        assert_eq!(err.key(), Some("fragmentSize"));
+        assert!(err.to_string().starts_with("expected utf-8"));
+        assert!(err.to_string().ends_with(" for key \"fragmentSize\" at byte 161"));
+
+        let mut src: &[u8] = &[0x66, 0x73, 0x79, 0x73, 0xc0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x04];
+        let err = FeslMessage::from_read(&mut src).unwrap_err();
+        assert_eq!(err.to_string(), "message length 4 is too small at byte 8");
+
+        let err: Error = ::std::io::Error::new(::std::io::ErrorKind::UnexpectedEof, "eof").into();
+        assert!(::std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn it_reports_gamespy_error_locations() {
+        use super::gamespy::*;
+
+        let packet = GameSpyPacket::from_box(b"\\lc\\1\\id\\\xff\\final\\".to_vec().into_boxed_slice());
+        let mut iter = packet.into_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), ("lc", "1"));
+        let err = iter.next().unwrap().unwrap_err();
+        assert_eq!(err.offset(), Some(9));
+        assert_eq!(err.key(), Some("id"));
+        assert!(iter.next().is_none());
+
+        let packet = GameSpyPacket::from_box(b"\\lc\\final\\".to_vec().into_boxed_slice());
+        match packet.into_iter().next().unwrap().map_err(Error::into_kind) {
+            Err(ErrorKind::ExpectedDelimiter) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[test]
+    fn it_parses_borrowed_messages() {
+        let mut src: Vec<u8> = Vec::new();
+        src.extend(HELLO);
+        src.extend(HELLO);
+        src.extend(&HELLO[..30]);
+
+        let mut buf = &src[..];
+        let mut count = 0;
+        while let Some(msg) = FeslMessageRef::parse(buf).unwrap() {
+            assert_eq!(msg.get_cmd().unwrap(), "fsys");
+            assert_eq!(msg.get_type().unwrap(), FeslMessageType::SingleClient);
+            assert_eq!(msg.get_id(), 1);
+            assert_eq!(msg.into_iter().nth(3).unwrap().unwrap(), ("locale", "en_US"));
+            assert_eq!(msg.as_bytes(), HELLO);
+            buf = &buf[msg.len()..];
+            count += 1;
+        }
+        assert_eq!(count, 2);
+        assert_eq!(buf.len(), 30);
+    }
+
+    #[test]
+    fn it_converts_owned_messages() {
+        use core::convert::TryFrom;
+
+        let msg = FeslMessage::try_from(HELLO.to_vec()).unwrap();
+        assert_eq!(msg.as_bytes(), HELLO);
+        let msg = FeslMessage::try_from(HELLO).unwrap();
+        assert_eq!(msg.as_message_ref().to_message().as_bytes(), HELLO);
+
+        match FeslMessage::try_from(&HELLO[..100]).map_err(Error::into_kind) {
+            Err(ErrorKind::Incomplete(100)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        let mut src = HELLO.to_vec();
+        src.push(0x00);
+        match FeslMessage::try_from(src).map_err(Error::into_kind) {
+            Err(ErrorKind::TrailingBytes(1)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[test]
+    fn it_builds_with_owned_values() {
+        // everything pushed is owned, so the builder outlives the strings it was made from
+        let mut builder: FeslOwnedMessageBuilder = {
+            let cmd = String::from("fsys");
+            let mut builder = FeslMessageBuilder::new(&cmd, FeslMessageType::SingleServer, 1);
+            builder.push("TXN", "Hello");
+            builder.push(String::from("theaterIp"), format!("{}.{}.{}.{}", 127, 0, 0, 1));
+            builder.push_u32("theaterPort", 18275);
+            builder.push_i64("curTime", -1);
+            builder.push_bool("domainPartition.exists", true);
+            builder
+        };
+        builder.push_raw("data", "VFhO%3d");
+        let msg = builder.build();
+
+        let mut iter = msg.into_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), ("TXN", "Hello"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("theaterIp", "127.0.0.1"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("theaterPort", "18275"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("curTime", "-1"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("domainPartition.exists", "true"));
+        assert_eq!(iter.next().unwrap().unwrap(), ("data", "VFhO%3d"));
+        assert!(iter.next().is_none());
+        assert_eq!(msg.get_cmd().unwrap(), "fsys");
+    }
+
+    #[test]
+    fn it_looks_up_keys() {
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleServer, 1);
+        builder.push("TXN", "Hello");
+        builder.push("theaterIp", "127.0.0.1");
+        builder.push_u32("theaterPort", 18275);
+        builder.push_i64("curTime", -1);
+        builder.push_bool("domainPartition.exists", true);
+        builder.push("TXN", "Duplicate");
+        builder.push_u32("sites.[]", 2);
+        builder.push("flag", "1");
+        builder.push_raw("quoted", "\"42\"");
+        let msg = builder.build();
+
+        assert_eq!(msg.get("TXN").unwrap().as_deref(), Some("Hello"));
+        assert_eq!(msg.get("missing").unwrap().as_deref(), None);
+        assert_eq!(msg.get_u32("theaterPort").unwrap(), Some(18275));
+        assert_eq!(msg.get_i64("curTime").unwrap(), Some(-1));
+        assert_eq!(msg.get_bool("domainPartition.exists").unwrap(), Some(true));
+        assert_eq!(msg.get_bool("flag").unwrap(), Some(true));
+        assert_eq!(msg.get_list_len("sites.[]").unwrap(), Some(2));
+        assert_eq!(msg.require("theaterIp").unwrap(), "127.0.0.1");
+        assert_eq!(msg.get_raw("quoted").unwrap(), Some("\"42\""));
+        assert_eq!(msg.get("quoted").unwrap().as_deref(), Some("42"));
+        assert_eq!(msg.get_u32("quoted").unwrap(), Some(42));
+
+        let error = msg.get_u32("TXN").unwrap_err();
+        assert_eq!(error.key(), Some("TXN"));
+        match error.into_kind() {
+            ErrorKind::InvalidValue(ref value) if value == "Hello" => (),
+            x => panic!("Unexpected error {:?}", x)
+        }
+        match msg.require_u32("missing").map_err(Error::into_kind) {
+            Err(ErrorKind::MissingKey(ref key)) if key == "missing" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[test]
+    fn it_edits_messages_in_place() {
+        use core::convert::TryFrom;
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleServer, 1);
+        builder.push("TXN", "Hello");
+        builder.push("theaterIp", "127.0.0.1");
+        builder.push_u32("theaterPort", 18275);
+        builder.push_i64("curTime", -1);
+        builder.push_bool("domainPartition.exists", true);
+        let mut msg = builder.build();
+        msg.set("theaterIp", "10.0.0.1").unwrap();
+        msg.set_raw("theaterPort", "18000").unwrap();
+        msg.set("locale", "en US").unwrap();
+        assert!(msg.remove("curTime").unwrap());
+        assert!(!msg.remove("curTime").unwrap());
+        assert!(msg.rename("domainPartition.exists", "domainPartition.valid").unwrap());
+        msg.set_id(0x1234567);
+        msg.set_type(FeslMessageType::SingleClient);
+
+        assert_eq!(msg.get("theaterIp").unwrap().as_deref(), Some("10.0.0.1"));
+        assert_eq!(msg.get_u32("theaterPort").unwrap(), Some(18000));
+        assert_eq!(msg.get("curTime").unwrap().as_deref(), None);
+        assert_eq!(msg.get_bool("domainPartition.valid").unwrap(), Some(true));
+        assert_eq!(msg.get_id(), 0x1234567);
+        assert_eq!(msg.get_type().unwrap(), FeslMessageType::SingleClient);
+
+        let copy = FeslMessage::try_from(msg.as_bytes()).unwrap();
+        let pairs: Vec<_> = copy.into_iter().map(Result::unwrap).collect();
+        assert_eq!(pairs, vec![
+            ("TXN", "Hello"),
+            ("theaterIp", "10.0.0.1"),
+            ("theaterPort", "18000"),
+            ("domainPartition.valid", "true"),
+            ("locale", "\"en US\"")
+        ]);
+
+        let before = msg.as_bytes().to_vec();
+        match msg.rename("theaterIp", "b\nc").map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidKey(ref key)) if key == "b\nc" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        match msg.set("a=b", "1").map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidKey(ref key)) if key == "a=b" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        match msg.set_raw("theaterIp", "1\0").map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidValue(ref value)) if value == "1\0" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        msg.set("theaterIp", "a\nb").unwrap();
+        assert_eq!(msg.get("theaterIp").unwrap().as_deref(), Some("a\nb"));
+        msg.set("theaterIp", "10.0.0.1").unwrap();
+        assert_eq!(msg.as_bytes(), &before[..]);
+    }
+
+    #[test]
+    fn it_edits_keys_after_non_utf8_values() {
+        use core::convert::TryFrom;
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 1);
+        builder.push("TXN", "NuLogin");
+        builder.push_raw("name", "Ren\u{e9}");
+        builder.push("locale", "fr_FR");
+        builder.push("country", "FR");
+        builder.push("curTime", "now");
+        let mut src = builder.build().as_bytes().to_vec();
+        src.splice(32..34, vec![0xe9]);
+        let len = src.len() as u8;
+        src[11] = len;
+        let mut msg = FeslMessage::try_from(src).unwrap();
+
+        msg.set("locale", "de_DE").unwrap();
+        assert!(msg.remove("curTime").unwrap());
+        assert!(msg.rename("country", "region").unwrap());
+        let raw: Vec<_> = msg.raw_iter().map(Result::unwrap).collect();
+        assert_eq!(raw, vec![
+            (&b"TXN"[..], &b"NuLogin"[..]),
+            (&b"name"[..], &b"Ren\xe9"[..]),
+            (&b"locale"[..], &b"de_DE"[..]),
+            (&b"region"[..], &b"FR"[..])
+        ]);
+        assert_eq!(msg.as_bytes()[8..12], (msg.as_bytes().len() as u32).to_be_bytes());
+    }
+
+    #[test]
+    fn it_preserves_unknown_types() {
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::Other(0xa0), 1);
+        builder.push("TXN", "Hello");
+        let mut msg = builder.build();
+        assert_eq!(msg.get_type_and_id(), 0xa0000001);
+        assert_eq!(FeslMessageType::from_bits(msg.as_bytes()[4]), FeslMessageType::Other(0xa0));
+        match msg.get_type().map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidType(0xa0)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert_eq!(msg.get_message_type(), FeslMessageType::Other(0xa0));
+        assert_eq!(msg.get_message_type().reply(), FeslMessageType::Other(0xa0));
+        assert_eq!(FeslMessageType::Other(0xc0), FeslMessageType::SingleClient);
+        assert_eq!(FeslMessageType::Other(0xf0).reply(), FeslMessageType::SingleServer);
+        assert!(FeslMessageType::Other(0xa0) != FeslMessageType::Other(0x90));
+
+        msg.set_type_and_id(0x90000002);
+        assert_eq!(msg.get_id(), 2);
+        msg.set_type(FeslMessageType::SingleServer);
+        assert_eq!(msg.get_type_and_id(), 0x80000002);
+        assert_eq!(msg.get_len_field() as usize, msg.as_bytes().len());
+        msg.set_len_field(0xffff);
+        assert_eq!(msg.get_len_field(), 0xffff);
+        assert_eq!(&msg.as_bytes()[8..12], &[0x00, 0x00, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn it_builds_with_commands() {
+        use core::convert::TryFrom;
+
+        match FeslMessageBuilder::try_new("abcde", FeslMessageType::SingleClient, 0).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidCommandLength) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert_eq!(FeslCommand::try_from("fsys").unwrap(), FeslCommand::FSYS);
+        assert!(FeslCommand::try_from("acc").is_err());
+
+        let mut builder = FeslMessageBuilder::with_command(FeslCommand::ACCT, FeslMessageType::SingleClient, 2);
+        builder.push("TXN", "NuLogin");
+        let msg = builder.build();
+        assert_eq!(msg.get_command(), FeslCommand::ACCT);
+        assert_eq!(msg.get_command(), "acct");
+        assert_eq!(msg.get_cmd().unwrap(), "acct");
+        assert_eq!(FeslCommand::new([0xff, 0x61, 0x62, 0x63]).to_string(), "\u{fffd}abc");
+    }
+
+    #[test]
+    fn it_round_trips_through_builder() {
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 1);
+        builder.push("name", "a b");
+        builder.push("line", "a\nb");
+        match builder.try_push("key=with\nbreak", "1").map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidKey(ref key)) if key == "key=with\nbreak" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        match builder.try_push_raw("a", "x\ny").map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidValue(ref value)) if value == "x\ny" => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert_eq!(builder.encoded_len(), 37);
+        let msg = builder.build();
+
+        let mut iter = msg.into_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), ("name", "\"a b\""));
+        assert_eq!(iter.next().unwrap().unwrap(), ("line", "\"a%0ab\""));
+        assert!(iter.next().is_none());
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 1);
+        builder.extend(msg.into_iter().unescaped().map(Result::unwrap));
+        assert_eq!(builder.build().as_bytes(), msg.as_bytes());
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 1);
+        for item in &msg {
+            let (key, value) = item.unwrap();
+            builder.push_raw(key, value);
+        }
+        assert_eq!(builder.build().as_bytes(), msg.as_bytes());
+    }
+
+    // accepts at most a few bytes per call, like a congested socket
+    #[cfg(feature = "std")]
+    struct ShortWriter(Vec<u8>);
+
+    #[cfg(feature = "std")]
+    impl std::io::Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(3);
+            self.0.extend(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_writes_builders_directly() {
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleServer, 1);
+        builder.push("TXN", "Hello");
+        builder.push("theaterIp", "127.0.0.1");
+        builder.push("empty", "");
+        let mut dst = ShortWriter(Vec::new());
+        builder.write_to(&mut dst).unwrap();
+        assert_eq!(builder.encoded_len(), dst.0.len());
+
+        let msg = builder.build();
+        assert_eq!(msg.as_bytes(), &dst.0[..]);
+        assert_eq!(msg.get_len_field() as usize, dst.0.len());
+    }
+
+    // counts write_vectored calls, accepting at most `limit` bytes from each
+    #[cfg(feature = "std")]
+    struct VectoredWriter {
+        buf: Vec<u8>,
+        calls: usize,
+        limit: usize
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Write for VectoredWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.write_vectored(&[std::io::IoSlice::new(buf)])
+        }
+
+        fn write_vectored(&mut self, bufs: &[std::io::IoSlice]) -> std::io::Result<usize> {
+            self.calls += 1;
+            let start = self.buf.len();
+            for buf in bufs {
+                let len = buf.len().min(self.limit - (self.buf.len() - start));
+                self.buf.extend(&buf[..len]);
+            }
+            Ok(self.buf.len() - start)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_writes_builders_in_vectored_batches() {
+        let mut builder = FeslMessageBuilder::new("rank", FeslMessageType::SingleServer, 1);
+        builder.push("TXN", "GetStats");
+        for i in 0..99 {
+            builder.push(format!("stats.{}", i), "x".repeat(i));
+        }
+        let mut dst = VectoredWriter { buf: Vec::new(), calls: 0, limit: usize::MAX };
+        builder.write_to(&mut dst).unwrap();
+        // the header, four slices per pair and the terminator, 64 slices per call
+        assert_eq!(dst.calls, 7);
+
+        let mut short = VectoredWriter { buf: Vec::new(), calls: 0, limit: 100 };
+        builder.write_to(&mut short).unwrap();
+        assert_eq!(short.buf, dst.buf);
+        assert_eq!(builder.build().as_bytes(), &dst.buf[..]);
+    }
+
+    #[test]
+    fn it_rejects_lines_without_delimiter() {
+        use core::convert::TryFrom;
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 1);
+        builder.push("TXN", "Hello");
+        builder.push_raw("broken key", "value");
+        let mut src = builder.build().as_bytes().to_vec();
+        src[28] = b'\n';
+        let msg = FeslMessage::try_from(src).unwrap();
+
+        let mut iter = msg.into_iter();
+        assert_eq!(iter.next().unwrap().unwrap(), ("TXN", "Hello"));
+        let err = iter.next().unwrap().unwrap_err();
+        assert_eq!(err.offset(), Some(22));
+        match err.into_kind() {
+            ErrorKind::ExpectedDelimiter => (),
+            x => panic!("Unexpected error {:?}", x)
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn it_iterates_non_utf8_values() {
+        use alloc::borrow::Cow;
+        use core::convert::TryFrom;
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 1);
+        builder.push("TXN", "NuLogin");
+        builder.push_raw("name", "Ren\u{e9}");
+        builder.push("locale", "fr_FR");
+        let mut src = builder.build().as_bytes().to_vec();
+        // replace the utf-8 encoding of \u{e9} with its single latin-1 byte
+        src.splice(32..34, vec![0xe9]);
+        let len = src.len() as u8;
+        src[11] = len;
+        let msg = FeslMessage::try_from(src).unwrap();
+
+        let raw: Vec<_> = msg.raw_iter().map(Result::unwrap).collect();
+        assert_eq!(raw[1], (&b"name"[..], &b"Ren\xe9"[..]));
+        assert_eq!(raw.len(), 3);
+
+        let mut iter = msg.raw_iter().decoded(FeslTextMode::Strict);
+        assert_eq!(iter.next().unwrap().unwrap(), (Cow::from("TXN"), Cow::from("NuLogin")));
+        let err = iter.next().unwrap().unwrap_err();
+        assert_eq!(err.key(), Some("name"));
+        assert_eq!(err.offset(), Some(32));
+        assert_eq!(iter.next().unwrap().unwrap(), (Cow::from("locale"), Cow::from("fr_FR")));
+        assert!(iter.next().is_none());
+
+        let lossy: Vec<_> = msg.raw_iter().decoded(FeslTextMode::Lossy).map(Result::unwrap).collect();
+        assert_eq!(lossy[1].1, "Ren\u{fffd}");
+        let latin1: Vec<_> = msg.raw_iter().decoded(FeslTextMode::Latin1).map(Result::unwrap).collect();
+        assert_eq!(latin1[1].1, "Ren\u{e9}");
+        assert_eq!(latin1[2].1, "fr_FR");
+    }
+
+    #[test]
+    fn it_looks_up_keys_after_non_utf8_values() {
+        use core::convert::TryFrom;
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 1);
+        builder.push("TXN", "NuLogin");
+        builder.push_raw("name", "Ren\u{e9}");
+        builder.push("locale", "fr_FR");
+        let mut src = builder.build().as_bytes().to_vec();
+        src.splice(32..34, vec![0xe9]);
+        let len = src.len() as u8;
+        src[11] = len;
+        let msg = FeslMessage::try_from(src.clone()).unwrap();
+
+        assert_eq!(msg.get("locale").unwrap().unwrap(), "fr_FR");
+        assert_eq!(msg.require("TXN").unwrap(), "NuLogin");
+        let err = msg.get("name").unwrap_err();
+        assert_eq!(err.key(), Some("name"));
+        assert_eq!(err.offset(), Some(29));
+        match msg.require("name").map_err(Error::into_kind) {
+            Err(ErrorKind::ExpectedUtf8(_)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert_eq!(msg.get("missing").unwrap(), None);
+
+        // a pair without its delimiter hides everything after it
+        src[28] = b'_';
+        let msg = FeslMessage::try_from(src).unwrap();
+        assert_eq!(msg.get("TXN").unwrap().unwrap(), "NuLogin");
+        match msg.get("locale").map_err(Error::into_kind) {
+            Err(ErrorKind::ExpectedDelimiter) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[test]
+    fn it_converts_fsys_transactions() {
+        use core::convert::TryFrom;
+
+        use super::fesl::fsys::*;
+
+        let msg = FeslMessage::try_from(HELLO).unwrap();
+        assert_eq!(Hello::CMD, FeslCommand::FSYS);
+        let hello = Hello::from_message(&msg).unwrap();
+        assert_eq!(hello.client_string, "mohair-pc");
+        assert_eq!(hello.sdk_version, "3.5.2.0.9");
+        assert_eq!(hello.fragment_size, 8096);
+        assert_eq!(hello.client_type, Some("server".to_string()));
+        assert_eq!(hello.to_message(1).as_bytes(), HELLO);
+
+        let response = HelloResponse {
+            domain_partition: DomainPartition {
+                domain: "eagames".to_string(),
+                sub_domain: "BF2142".to_string()
+            },
+            cur_time: "Oct-18-2026 12:00:00 UTC".to_string(),
+            activity_timeout_secs: 3600,
+            messenger_ip: None,
+            messenger_port: None,
+            theater_ip: "127.0.0.1".to_string(),
+            theater_port: 18275
+        };
+        let msg = response.to_message(1);
+        assert_eq!(msg.get_type().unwrap(), FeslMessageType::SingleServer);
+        assert_eq!(msg.get("domainPartition.subDomain").unwrap().as_deref(), Some("BF2142"));
+        assert_eq!(msg.get("curTime").unwrap().as_deref(), Some("Oct-18-2026 12:00:00 UTC"));
+        assert_eq!(msg.get_raw("curTime").unwrap(), Some("\"Oct-18-2026 12:00:00 UTC\""));
+        assert_eq!(HelloResponse::from_message(&msg).unwrap(), response);
+        assert!(Hello::from_message(&msg).is_err());
+
+        let sites = GetPingSitesResponse {
+            min_ping_sites_to_ping: 0,
+            ping_sites: vec![PingSite { addr: "10.0.0.1".to_string(), name: "gva".to_string(), site_type: 1 }]
+        };
+        let msg = sites.to_message(2);
+        assert_eq!(msg.get_list_len("pingSite.[]").unwrap(), Some(1));
+        assert_eq!(msg.get("pingSite.0.type").unwrap().as_deref(), Some("1"));
+        assert_eq!(GetPingSitesResponse::from_message(&msg).unwrap(), sites);
+
+        let memcheck = MemCheck { memcheck: Vec::new(), check_type: 0, salt: 536879839 };
+        let msg = memcheck.to_message(0);
+        assert_eq!(msg.get("memcheck.[]").unwrap().as_deref(), Some("0"));
+        assert_eq!(MemCheck::from_message(&msg).unwrap(), memcheck);
+        assert_eq!(Ping::from_message(&Ping {}.to_message(0)).unwrap(), Ping {});
+    }
+
+    #[test]
+    fn it_converts_acct_transactions() {
+        use super::fesl::acct;
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 2);
+        builder.push("TXN", "NuLogin");
+        builder.push("returnEncryptedInfo", "0");
+        builder.push("encryptedInfo", "Ciyvab0tregdVsBtboIpeChe4G6uzC1v5_-SIxmvSLI");
+        let login = acct::NuLogin::from_message(&builder.build()).unwrap();
+        assert_eq!(login.return_encrypted_info, Some(false));
+        assert_eq!(login.nuid, None);
+        assert_eq!(login.encrypted_info.as_ref().map(|x| &x[..]), Some("Ciyvab0tregdVsBtboIpeChe4G6uzC1v5_-SIxmvSLI"));
+
+        let response = acct::NuLoginResponse {
+            lkey: "W8NyEzR4cKW8ynVy8rJmIn9hWbGFUhTCJLZaHXQS".to_string(),
+            nuid: "user@example.com".to_string(),
+            profile_id: 1000,
+            user_id: 1001,
+            display_name: Some("Player One".to_string()),
+            encrypted_login_info: None
+        };
+        let msg = response.to_message(2);
+        assert_eq!(msg.require_u32("userId").unwrap(), 1001);
+        assert_eq!(msg.get("displayName").unwrap().as_deref(), Some("Player One"));
+        assert_eq!(msg.get_raw("displayName").unwrap(), Some("\"Player One\""));
+        assert_eq!(msg.get("encryptedLoginInfo").unwrap().as_deref(), None);
+        assert_eq!(acct::NuLoginResponse::from_message(&msg).unwrap(), response);
+
+        let personas = acct::NuGetPersonasResponse {
+            personas: vec!["foo".to_string(), "bar".to_string()]
+        };
+        let msg = personas.to_message(3);
+        assert_eq!(msg.get("personas.1").unwrap().as_deref(), Some("bar"));
+        assert_eq!(acct::NuGetPersonasResponse::from_message(&msg).unwrap(), personas);
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleServer, 4);
+        builder.push("TXN", "NuLoginPersona");
+        builder.push("lkey", "abc");
+        builder.push("profileId", "x");
+        builder.push("userId", "2");
+        match acct::NuLoginPersonaResponse::from_message(&builder.build()) {
+            Err(ref err) if err.key() == Some("profileId") => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+    }
+
+    #[test]
+    fn it_builds_error_responses() {
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 7);
+        builder.push("TXN", "NuAddPersona");
+        builder.push("name", "x");
+        let request = builder.build();
+
+        let error = FeslErrorResponse::new(FeslErrorCode::FieldInvalid).with_field("name", 3, Some("TOO_SHORT"));
+        let reply = error.to_reply(&request).unwrap();
+        assert_eq!(reply.get_command(), FeslCommand::ACCT);
+        assert_eq!(reply.get_type().unwrap(), FeslMessageType::SingleServer);
+        assert_eq!(reply.get_id(), 7);
+
+        let pairs: Vec<_> = reply.into_iter().map(Result::unwrap).collect();
+        assert_eq!(pairs, vec![
+            ("TXN", "NuAddPersona"),
+            ("localizedMessage", "\"The required parameters for this call are missing or invalid\""),
+            ("errorContainer.[]", "1"),
+            ("errorContainer.0.fieldName", "name"),
+            ("errorContainer.0.fieldError", "3"),
+            ("errorContainer.0.value", "TOO_SHORT"),
+            ("errorCode", "21")
+        ]);
+        assert_eq!(FeslErrorResponse::from_message(&reply).unwrap(), Some(error));
+
+        let reply = FeslErrorResponse::new(FeslErrorCode::from_code(122)).to_reply(&request).unwrap();
+        let error = FeslErrorResponse::from_message(&reply).unwrap().unwrap();
+        assert_eq!(error.code, FeslErrorCode::InvalidPassword);
+        assert_eq!(error.message, "The password the user specified is incorrect");
+        assert!(error.fields.is_empty());
+        assert_eq!(FeslErrorCode::from_code(999).code(), 999);
+        assert_eq!(FeslErrorResponse::from_message(&request).unwrap(), None);
+    }
+
+    #[test]
+    fn it_routes_transactions() {
+        use super::fesl::{acct, fsys};
+
+        #[derive(Default)]
+        struct Connection {
+            logins: u32,
+            goodbye: bool
+        }
+
+        let mut router: FeslRouter<Connection> = FeslRouter::new();
+        router.on_transaction(|ctx: &mut Connection, request: acct::NuLoginPersona| {
+            ctx.logins += 1;
+            Ok(FeslReply::from_transaction(&acct::NuLoginPersonaResponse {
+                lkey: format!("lkey-{}", request.name),
+                profile_id: 1,
+                user_id: ctx.logins
+            }))
+        });
+        router.on_command(FeslCommand::FSYS, "Goodbye", |ctx, _| {
+            ctx.goodbye = true;
+            Ok(FeslReply::None)
+        });
+
+        let mut ctx = Connection::default();
+        let request = acct::NuLoginPersona { name: "foo".to_string() }.to_message(5);
+        let reply = router.dispatch(&mut ctx, &request).unwrap().unwrap().build();
+        assert_eq!(reply.get_command(), FeslCommand::ACCT);
+        assert_eq!(reply.get_type().unwrap(), FeslMessageType::SingleServer);
+        assert_eq!(reply.get_id(), 5);
+        assert_eq!(reply.get("lkey").unwrap().as_deref(), Some("lkey-foo"));
+        assert_eq!(reply.into_iter().filter(|x| x.as_ref().unwrap().0 == "TXN").count(), 1);
+        assert_eq!(ctx.logins, 1);
+
+        let goodbye = fsys::Goodbye { reason: "GOODBYE_CLIENT_NORMAL".to_string(), message: None }.to_message(6);
+        assert!(router.dispatch(&mut ctx, &goodbye).unwrap().is_none());
+        assert!(ctx.goodbye);
+
+        let mut builder = FeslMessageBuilder::new("acct", FeslMessageType::SingleClient, 7);
+        builder.push("TXN", "NuLoginPersona");
+        let request = builder.build();
+        let reply = router.dispatch(&mut ctx, &request).unwrap().unwrap().build();
+        let error = FeslErrorResponse::from_message(&reply).unwrap().unwrap();
+        assert_eq!(error.code, FeslErrorCode::FieldInvalid);
+        assert_eq!(error.fields[0].field_name, "name");
+        assert_eq!(ctx.logins, 1);
+
+        let unknown = fsys::GetPingSites {}.to_message(8);
+        let reply = router.dispatch(&mut ctx, &unknown).unwrap().unwrap().build();
+        assert_eq!(reply.get("TXN").unwrap().as_deref(), Some("GetPingSites"));
+        assert_eq!(reply.get_u32("errorCode").unwrap(), Some(99));
+
+        router.set_fallback(|_, _| Ok(FeslReply::None));
+        assert!(router.dispatch(&mut ctx, &unknown).unwrap().is_none());
+
+        router.on_command(FeslCommand::FSYS, "GetPingSites", |_, _| {
+            Ok(FeslReply::from_transaction(&fsys::GetPingSitesResponse {
+                min_ping_sites_to_ping: 0,
+                ping_sites: (0..10).map(|i| fsys::PingSite {
+                    addr: format!("10.0.0.{}", i),
+                    name: format!("site-{}", i),
+                    site_type: 1
+                }).collect()
+            }))
+        });
+        let fragments = router.dispatch(&mut ctx, &unknown).unwrap().unwrap().build_fragments(200).unwrap();
+        assert!(fragments.len() > 1);
+        let mut assembler = FeslFragmentAssembler::new();
+        let reply = fragments.into_iter().filter_map(|x| assembler.push(x).unwrap()).next().unwrap();
+        assert_eq!(fsys::GetPingSitesResponse::from_message(&reply).unwrap().ping_sites.len(), 10);
+    }
+
+    // an in-memory transport that replays scripted server messages and records what the client sends
+    #[cfg(feature = "std")]
+    struct Pipe {
+        input: std::io::Cursor<Vec<u8>>,
+        output: Vec<u8>
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_correlates_client_replies() {
+        use super::fesl::{acct, fsys};
+
+        let mut input = Vec::new();
+        input.extend(fsys::Ping {}.to_message(0).as_bytes());
+        input.extend(fsys::GetPingSitesResponse { min_ping_sites_to_ping: 0, ping_sites: Vec::new() }.to_message(2).as_bytes());
+        input.extend(acct::NuGetAccount {}.to_message(9).as_bytes());
+        input.extend(acct::NuLogin { return_encrypted_info: None, nuid: None, password: None, encrypted_info: None, mac_addr: None }.to_message(1).as_bytes());
+        input.extend(acct::NuLoginPersonaResponse { lkey: "abc".to_string(), profile_id: 1, user_id: 2 }.to_message(3).as_bytes());
+        let mut client = FeslClient::new(Pipe { input: std::io::Cursor::new(input), output: Vec::new() });
+
+        let first = client.send_transaction(&acct::NuLoginPersona { name: "foo".to_string() }).unwrap();
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 0);
+        builder.push("TXN", "GetPingSites");
+        let second = client.send(builder).unwrap();
+        assert_eq!((first, second), (1, 2));
+        assert_eq!(client.pending(), 2);
+
+        match client.wait(first).map_err(Error::into_kind) {
+            Err(ErrorKind::MismatchedReply(1)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+        assert_eq!(client.wait(second).unwrap().get("TXN").unwrap().as_deref(), Some("GetPingSites"));
+        assert_eq!(client.try_unsolicited().unwrap().get("TXN").unwrap().as_deref(), Some("Ping"));
+        assert!(client.try_unsolicited().is_none());
+        assert_eq!(client.next_orphan().unwrap().get_id(), 9);
+        assert!(client.next_orphan().is_none());
+        match client.wait(second).map_err(Error::into_kind) {
+            Err(ErrorKind::UnknownRequest(2)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        let response: acct::NuLoginPersonaResponse = client.call_transaction(&acct::NuLoginPersona { name: "bar".to_string() }).unwrap();
+        assert_eq!(response.lkey, "abc");
+        assert_eq!(client.pending(), 0);
+
+        let mut decoder = FeslDecoder::new();
+        decoder.push(&client.into_inner().output);
+        let sent: Vec<_> = decoder.map(Result::unwrap).collect();
+        assert_eq!(sent.iter().map(FeslMessage::get_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(sent[1].get_type().unwrap(), FeslMessageType::SingleClient);
+        assert_eq!(sent[2].get("name").unwrap().as_deref(), Some("bar"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_bounds_client_queues() {
+        let mut input = Vec::new();
+        for (id, n) in [(0, 1), (7, 2), (0, 3), (8, 4), (0, 5), (9, 6), (1, 7)] {
+            let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleServer, id);
+            builder.push("TXN", "Ping");
+            builder.push_u32("n", n);
+            input.extend(builder.build().as_bytes());
+        }
+        let mut client = FeslClient::with_policy(Pipe { input: std::io::Cursor::new(input), output: Vec::new() }, FeslClientPolicy {
+            max_pending: 1,
+            max_queued: 2,
+            ..FeslClientPolicy::default()
+        });
+
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 0);
+        builder.push("TXN", "Ping");
+        let id = client.send(builder).unwrap();
+        let mut builder = FeslMessageBuilder::new("fsys", FeslMessageType::SingleClient, 0);
+        builder.push("TXN", "Ping");
+        match client.send(builder).map_err(Error::into_kind) {
+            Err(ErrorKind::TooManyPending(1)) => (),
+            x => panic!("Unexpected result {:?}", x)
+        }
+
+        assert_eq!(client.wait(id).unwrap().get_u32("n").unwrap(), Some(7));
+        let pushes: Vec<_> = client.try_unsolicited().into_iter().chain(client.try_unsolicited()).collect();
+        assert_eq!(pushes.iter().map(|x| x.get_u32("n").unwrap().unwrap()).collect::<Vec<_>>(), vec![3, 5]);
+        assert!(client.try_unsolicited().is_none());
+        assert_eq!(client.next_orphan().unwrap().get_id(), 8);
+        assert_eq!(client.next_orphan().unwrap().get_id(), 9);
+        assert!(client.next_orphan().is_none());
     }
 }